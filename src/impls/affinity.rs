@@ -0,0 +1,277 @@
+use std::{
+    fmt::Display,
+    fs, io,
+    sync::{
+        Arc, LazyLock, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use tokio::runtime::Runtime;
+
+/// Which CPUs threads are allowed to run on, and whether each thread gets a core of its own
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AffinityConfig {
+    /// Empty means every CPU the process was started with
+    pub cpus: Vec<usize>,
+    /// Pin each thread to a single CPU, handed out round robin, instead of letting it float over `cpus`
+    pub pin_threads: bool,
+}
+
+impl AffinityConfig {
+    /// Parses a list in the same format as `taskset -c`, e.g. `0-3,6`. Only CPUs the
+    /// process was started with are accepted
+    pub fn parse_cpu_list(list: &str) -> Result<Vec<usize>, String> {
+        parse_cpu_list_within(list, startup_cpus())
+    }
+
+    fn cpu_set(&self) -> Vec<usize> {
+        if self.cpus.is_empty() {
+            startup_cpus().to_vec()
+        } else {
+            self.cpus.clone()
+        }
+    }
+
+    /// Lets the calling thread float over the configured CPUs. "all" widens it back to
+    /// the startup CPUs, undoing an earlier "Apply to process" with fewer
+    pub fn restrict_current_thread(&self) {
+        let _ = set_thread_affinity(0, &self.cpu_set());
+    }
+
+    /// Pins the calling thread to the `index`th configured CPU when pinning is enabled,
+    /// otherwise only restricts it to the configured set
+    pub fn pin_current_thread(&self, index: usize) {
+        if !self.pin_threads {
+            return self.restrict_current_thread();
+        }
+        let cpus = self.cpu_set();
+        if let Some(cpu) = cpus.get(index % cpus.len().max(1)) {
+            let _ = set_thread_affinity(0, &[*cpu]);
+        }
+    }
+
+    /// A multi-threaded tokio runtime whose workers are pinned like OS threads would be
+    pub fn multi_thread_runtime(&self) -> Runtime {
+        let config = self.clone();
+        let next = Arc::new(AtomicUsize::new(0));
+        let next_name = AtomicUsize::new(0);
+        tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .thread_name_fn(move || {
+                format!("bench-tokio-{}", next_name.fetch_add(1, Ordering::Relaxed))
+            })
+            .on_thread_start(move || {
+                config.pin_current_thread(next.fetch_add(1, Ordering::Relaxed));
+            })
+            .build()
+            .expect("tokio runtime")
+    }
+
+    /// Applies the configuration to every thread that currently exists in the process.
+    /// Tokio workers get pinned, everything else is restricted to the set, and threads
+    /// spawned later inherit the mask of whoever spawns them
+    pub fn apply_to_process(&self) -> io::Result<()> {
+        let cpus = self.cpu_set();
+        let mut next_worker = 0;
+        for entry in fs::read_dir("/proc/self/task")? {
+            let entry = entry?;
+            let Some(tid) = entry.file_name().to_str().and_then(|t| t.parse().ok()) else {
+                continue;
+            };
+            let name = fs::read_to_string(entry.path().join("comm")).unwrap_or_default();
+            let result = if self.pin_threads && name.starts_with("tokio-worker-") {
+                let cpu = cpus[next_worker % cpus.len()];
+                next_worker += 1;
+                set_thread_affinity(tid, &[cpu])
+            } else {
+                set_thread_affinity(tid, &cpus)
+            };
+            match result {
+                //The thread exited after the directory was listed
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                result => result?,
+            }
+        }
+        *MODEL_AFFINITY.lock().unwrap() = self.clone();
+        Ok(())
+    }
+}
+
+impl Display for AffinityConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.cpus.is_empty() {
+            f.write_str("all")?;
+        } else {
+            let cpus: Vec<String> = self.cpus.iter().map(usize::to_string).collect();
+            f.write_str(&cpus.join(","))?;
+        }
+        if self.pin_threads {
+            f.write_str(" pinned")?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_cpu_list_within(list: &str, allowed: &[usize]) -> Result<Vec<usize>, String> {
+    let mut cpus = Vec::new();
+    for part in list.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let parse = |n: &str| match n.trim().parse::<usize>() {
+            Ok(cpu) if cpu < MAX_CPUS => Ok(cpu),
+            Ok(cpu) => Err(format!(
+                "CPU {cpu} is beyond the {MAX_CPUS} CPUs a mask can hold"
+            )),
+            Err(_) => Err(format!("invalid CPU number '{n}'")),
+        };
+        //Both ends are below MAX_CPUS, which bounds how far a range can expand
+        match part.split_once('-') {
+            Some((from, to)) => {
+                let (from, to) = (parse(from)?, parse(to)?);
+                if from > to {
+                    return Err(format!("empty CPU range '{part}'"));
+                }
+                cpus.extend(from..=to)
+            }
+            None => cpus.push(parse(part)?),
+        }
+    }
+    cpus.sort_unstable();
+    cpus.dedup();
+    if let Some(cpu) = cpus.iter().find(|cpu| !allowed.contains(cpu)) {
+        return Err(format!("CPU {cpu} is not available to this process"));
+    }
+    Ok(cpus)
+}
+
+/// The configuration last applied to the process, used by threads the models spawn afterwards
+static MODEL_AFFINITY: Mutex<AffinityConfig> = Mutex::new(AffinityConfig {
+    cpus: Vec::new(),
+    pin_threads: false,
+});
+static NEXT_MODEL_THREAD: AtomicUsize = AtomicUsize::new(0);
+
+/// Called at the start of every OS thread a model spawns, so One-to-One threads
+/// get a core of their own when pinning is enabled
+pub fn pin_model_thread() {
+    let config = MODEL_AFFINITY.lock().unwrap().clone();
+    if config.pin_threads {
+        config.pin_current_thread(NEXT_MODEL_THREAD.fetch_add(1, Ordering::Relaxed));
+    }
+}
+
+/// The affinity mask of the main thread before anything changed it
+static STARTUP_CPUS: LazyLock<Vec<usize>> = LazyLock::new(available_cpus);
+
+/// The CPUs the process was started with. `main` reads this first thing, before
+/// any affinity is applied, so an empty config can always widen back to it
+pub fn startup_cpus() -> &'static [usize] {
+    &STARTUP_CPUS
+}
+
+/// The CPUs the calling thread may currently run on
+pub fn available_cpus() -> Vec<usize> {
+    thread_affinity(0).unwrap_or_else(|_| {
+        let n = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1);
+        (0..n).collect()
+    })
+}
+
+/// How many CPUs a `cpu_set_t` can hold
+#[cfg(target_os = "linux")]
+const MAX_CPUS: usize = libc::CPU_SETSIZE as usize;
+#[cfg(not(target_os = "linux"))]
+const MAX_CPUS: usize = 1024;
+
+/// `tid` 0 means the calling thread. CPUs a mask cannot hold are left out.
+/// Fails with `NotFound` when the thread no longer exists
+#[cfg(target_os = "linux")]
+pub fn set_thread_affinity(tid: libc::pid_t, cpus: &[usize]) -> io::Result<()> {
+    // SAFETY: cpu_set_t is a plain bitmask for which all zeroes is the empty set.
+    // CPU_SET does not bounds check, so only CPUs below CPU_SETSIZE are passed to it
+    let result = unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        for cpu in cpus.iter().filter(|cpu| **cpu < MAX_CPUS) {
+            libc::CPU_SET(*cpu, &mut set);
+        }
+        libc::sched_setaffinity(tid, std::mem::size_of::<libc::cpu_set_t>(), &set)
+    };
+    if result == 0 {
+        return Ok(());
+    }
+    let e = io::Error::last_os_error();
+    if e.raw_os_error() == Some(libc::ESRCH) {
+        Err(io::Error::new(io::ErrorKind::NotFound, e))
+    } else {
+        Err(e)
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub fn thread_affinity(tid: libc::pid_t) -> io::Result<Vec<usize>> {
+    // SAFETY: see set_thread_affinity
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        if libc::sched_getaffinity(tid, std::mem::size_of::<libc::cpu_set_t>(), &mut set) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok((0..libc::CPU_SETSIZE as usize)
+            .filter(|cpu| libc::CPU_ISSET(*cpu, &set))
+            .collect())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn set_thread_affinity(_tid: i32, _cpus: &[usize]) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn thread_affinity(_tid: i32) -> io::Result<Vec<usize>> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALLOWED: [usize; 8] = [0, 1, 2, 3, 4, 5, 6, 7];
+
+    #[test]
+    fn parses_taskset_lists() {
+        assert_eq!(parse_cpu_list_within("", &ALLOWED), Ok(vec![]));
+        assert_eq!(
+            parse_cpu_list_within("0-3,6", &ALLOWED),
+            Ok(vec![0, 1, 2, 3, 6])
+        );
+        assert_eq!(
+            parse_cpu_list_within(" 5, 1-2 ,2,", &ALLOWED),
+            Ok(vec![1, 2, 5])
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_lists() {
+        assert!(parse_cpu_list_within("a", &ALLOWED).is_err());
+        assert!(parse_cpu_list_within("1-", &ALLOWED).is_err());
+        assert!(parse_cpu_list_within("-1", &ALLOWED).is_err());
+        assert!(parse_cpu_list_within("3-1", &ALLOWED).is_err());
+    }
+
+    #[test]
+    fn rejects_cpus_outside_the_process() {
+        assert!(parse_cpu_list_within("8", &ALLOWED).is_err());
+        assert!(parse_cpu_list_within("2000", &ALLOWED).is_err());
+        assert!(parse_cpu_list_within("0-999999999", &ALLOWED).is_err());
+        assert!(parse_cpu_list_within("1,3", &[0, 1, 2]).is_err());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn vanished_threads_are_not_found() {
+        //Above the kernel's largest pid_max, so no thread can have it
+        let e = set_thread_affinity(1 << 23, startup_cpus()).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::NotFound);
+    }
+}
@@ -0,0 +1,204 @@
+use std::{
+    collections::VecDeque,
+    f32::consts::TAU,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use egui::{
+    Align2, Color32, Context, DragValue, FontId, Id, Pos2, Rect, Sense, Stroke, StrokeKind, Vec2,
+    pos2, vec2,
+};
+
+use crate::impls::foreground::{Blocker, ForegroundApp};
+
+/// Frame intervals shown in the history strip
+const HISTORY: usize = 240;
+/// How long a stall stays flagged after it happened
+const STALL_MARKER: Duration = Duration::from_secs(1);
+const CANVAS: Vec2 = vec2(420.0, 200.0);
+
+/// Animates continuously, so any frame this app does not get to draw shows up as a
+/// jump in the animation and a red marker, visible from across a lecture hall
+pub struct AnimationApp {
+    id: usize,
+    started: Instant,
+    last_frame: Option<Instant>,
+    frames: u64,
+    intervals: VecDeque<Duration>,
+    threshold_ms: u64,
+    stalls: u64,
+    /// When the last stall ended and how long the gap was
+    last_stall: Option<(Instant, Duration)>,
+}
+
+impl AnimationApp {
+    pub fn new(id: usize) -> Self {
+        Self {
+            id,
+            started: Instant::now(),
+            last_frame: None,
+            frames: 0,
+            intervals: VecDeque::with_capacity(HISTORY),
+            threshold_ms: 50,
+            stalls: 0,
+            last_stall: None,
+        }
+    }
+
+    fn threshold(&self) -> Duration {
+        Duration::from_millis(self.threshold_ms)
+    }
+
+    fn record_frame(&mut self, now: Instant) {
+        self.frames += 1;
+        if let Some(last) = self.last_frame {
+            let interval = now - last;
+            if self.intervals.len() == HISTORY {
+                self.intervals.pop_front();
+            }
+            self.intervals.push_back(interval);
+            if interval > self.threshold() {
+                self.stalls += 1;
+                self.last_stall = Some((now, interval));
+            }
+        }
+        self.last_frame = Some(now);
+    }
+
+    fn paint_clock(&self, painter: &egui::Painter, center: Pos2, radius: f32, color: Color32) {
+        painter.circle_stroke(center, radius, Stroke::new(3.0, color));
+        for tick in 0..12 {
+            let direction = Vec2::angled(tick as f32 / 12.0 * TAU);
+            painter.line_segment(
+                [
+                    center + direction * radius * 0.85,
+                    center + direction * radius,
+                ],
+                Stroke::new(2.0, color),
+            );
+        }
+        //Wall clock time in UTC, the second hand sweeps rather than ticks
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or_default();
+        let seconds = (millis % 60_000) as f32 / 1000.0;
+        let minutes = (millis / 60_000 % 60) as f32 + seconds / 60.0;
+        let hours = (millis / 3_600_000 % 12) as f32 + minutes / 60.0;
+        //Angle zero points right, so turn the hands back a quarter to start at twelve
+        let hand = |fraction: f32, length: f32, width: f32, color: Color32| {
+            let direction = Vec2::angled(fraction * TAU - TAU / 4.0);
+            painter.line_segment(
+                [center, center + direction * radius * length],
+                Stroke::new(width, color),
+            );
+        };
+        hand(hours / 12.0, 0.5, 5.0, color);
+        hand(minutes / 60.0, 0.75, 3.0, color);
+        hand(seconds / 60.0, 0.9, 1.5, Color32::RED);
+    }
+
+    fn paint_ball(&self, painter: &egui::Painter, area: Rect, color: Color32) {
+        painter.rect_stroke(area, 4.0, Stroke::new(1.0, color), StrokeKind::Inside);
+        let radius = 14.0;
+        let travel = area.width() - 2.0 * radius;
+        //A triangle wave of the elapsed time, so a skipped frame makes the ball jump
+        let phase = (self.started.elapsed().as_secs_f32() * 0.75).fract() * 2.0;
+        let offset = if phase < 1.0 { phase } else { 2.0 - phase };
+        let center = pos2(area.left() + radius + offset * travel, area.center().y);
+        painter.circle_filled(center, radius, Color32::from_rgb(80, 160, 255));
+    }
+
+    /// One bar per frame interval, red above the threshold
+    fn paint_history(&self, painter: &egui::Painter, area: Rect, color: Color32) {
+        let scale = area.height() / (self.threshold().as_secs_f32() * 4.0);
+        let bar_width = area.width() / HISTORY as f32;
+        let threshold_y = area.bottom() - self.threshold().as_secs_f32() * scale;
+        for (i, interval) in self.intervals.iter().enumerate() {
+            let height = (interval.as_secs_f32() * scale).min(area.height());
+            let left = area.left() + i as f32 * bar_width;
+            let bar = Rect::from_min_max(
+                pos2(left, area.bottom() - height),
+                pos2(left + bar_width, area.bottom()),
+            );
+            let color = if *interval > self.threshold() {
+                Color32::RED
+            } else {
+                Color32::from_rgb(80, 200, 120)
+            };
+            painter.rect_filled(bar, 0.0, color);
+        }
+        painter.line_segment(
+            [
+                pos2(area.left(), threshold_y),
+                pos2(area.right(), threshold_y),
+            ],
+            Stroke::new(1.0, color),
+        );
+    }
+}
+
+impl ForegroundApp for AnimationApp {
+    fn show(&mut self, ctx: &Context, _blocker: Blocker) {
+        let now = Instant::now();
+        self.record_frame(now);
+
+        let title = format!("Animation (fg-{})", self.id);
+        egui::Window::new(title)
+            .id(Id::new(("Animation", self.id)))
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(format!("Frame {}", self.frames));
+                    ui.separator();
+                    ui.label(format!("Stalls: {}", self.stalls));
+                    ui.separator();
+                    ui.label("Stall threshold: ");
+                    ui.add(
+                        DragValue::new(&mut self.threshold_ms)
+                            .range(5..=2000)
+                            .suffix(" ms"),
+                    );
+                });
+
+                let color = ui.visuals().text_color();
+                let (response, painter) = ui.allocate_painter(CANVAS, Sense::hover());
+                let rect = response.rect;
+                let clock_radius = rect.height() * 0.4;
+                let clock_center = pos2(
+                    rect.left() + rect.height() / 2.0,
+                    rect.top() + rect.height() * 0.45,
+                );
+                self.paint_clock(&painter, clock_center, clock_radius, color);
+                let ball_area = Rect::from_min_max(
+                    pos2(rect.left() + rect.height(), rect.top() + 10.0),
+                    pos2(rect.right(), rect.top() + 60.0),
+                );
+                self.paint_ball(&painter, ball_area, color);
+                let history_area = Rect::from_min_max(
+                    pos2(rect.left() + rect.height(), rect.top() + 80.0),
+                    pos2(rect.right(), rect.bottom()),
+                );
+                self.paint_history(&painter, history_area, color);
+
+                if let Some((at, gap)) = self.last_stall
+                    && now - at < STALL_MARKER
+                {
+                    painter.rect_stroke(
+                        rect,
+                        4.0,
+                        Stroke::new(6.0, Color32::RED),
+                        StrokeKind::Inside,
+                    );
+                    painter.text(
+                        history_area.center(),
+                        Align2::CENTER_CENTER,
+                        format!("STALL {gap:.0?}"),
+                        FontId::proportional(28.0),
+                        Color32::RED,
+                    );
+                }
+            });
+        ctx.request_repaint();
+    }
+}
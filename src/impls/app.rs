@@ -1,30 +1,94 @@
-use std::sync::{
-    Arc,
-    atomic::{AtomicU64, Ordering},
+use std::{
+    path::Path,
+    sync::{
+        Arc, Mutex, PoisonError,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
-use egui::{Button, CentralPanel, DragValue, Pos2, ProgressBar};
+use egui::{
+    Align2, Button, CentralPanel, Color32, DragValue, FontId, Pos2, ProgressBar, Sense, vec2,
+};
 use memory_stats::memory_stats;
+use tracing::{info, info_span};
 
 use crate::impls::{
     PROGRESS_MAX,
+    affinity::AffinityConfig,
     async_light_thread::ManyToManyModel,
     async_os_thread::OneToOneModel,
+    bench::{BenchControl, BenchReport, MAX_TOTAL_MEMORY_KIB, Workload},
+    bench_history::{self, BenchRecord, HISTORY_FILE},
+    event_log::{self, EventKind},
+    foreground::AppKind,
+    migration,
+    proc_stats::{ProcStats, REFRESH_INTERVAL, ThreadStat},
+    runtime_metrics::RuntimeStats,
     sync::ManyToOneModel,
     thread_model::{ThreadModel, ThreadModelKind},
+    thread_names::{ThreadRole, spawn_named},
+    timeline::{self, TimelineView},
+    trace,
 };
 
 pub const DEFAULT_TEXT: &str = "Lorem Ipsum is simply dummy text of the printing and typesetting industry. Lorem Ipsum has been the industry's standard dummy text ever since the 1500s, when an unknown printer took a galley of type and scrambled it to make a type specimen book. It has survived not only five centuries, but also the leap into electronic typesetting, remaining essentially unchanged. It was popularised in the 1960s with the release of Letraset sheets containing Lorem Ipsum passages, and more recently with desktop publishing software like Aldus PageMaker including versions of Lorem Ipsum.";
 pub const DEFAULT_NAME: &str = "First Last";
 pub const DEFAULT_AGE: u32 = 42;
 
+/// Clears `running` when the bench thread ends, even while a panicking benchmark unwinds,
+/// so the bench buttons never stay disabled
+struct BenchDone {
+    name: &'static str,
+    running: Arc<AtomicBool>,
+    result: Arc<Mutex<Option<String>>>,
+}
+
+impl std::ops::Drop for BenchDone {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            let name = self.name;
+            *self.result.lock().unwrap_or_else(PoisonError::into_inner) =
+                Some(format!("The {name} benchmark panicked"));
+            event_log::log(EventKind::Benchmark, format!("{name} benchmark panicked"));
+        }
+        self.running.store(false, Ordering::Relaxed);
+    }
+}
+
 pub struct App {
     model: Box<dyn ThreadModel>,
     foreground_tasks_started: bool,
+    /// Id of the next foreground app instance, restarting with every model
+    next_foreground_id: usize,
+    launch_kind: AppKind,
     background_task_spawn_num: u32,
     counter: Arc<AtomicU64>,
     bench_result: std::sync::Arc<std::sync::Mutex<Option<String>>>,
     bench_running: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    bench_control: Arc<BenchControl>,
+    io_latency_ms: u64,
+    bench_history: Arc<Mutex<Vec<BenchRecord>>>,
+    show_bench_history: bool,
+    proc_stats: ProcStats,
+    show_proc_stats: bool,
+    show_thread_inspector: bool,
+    show_timeline: bool,
+    /// Whether Many-to-Many uses the shared work stealing runtime or one runtime per worker
+    work_stealing: bool,
+    show_task_placement: bool,
+    runtime_stats: RuntimeStats,
+    show_runtime_metrics: bool,
+    trace_status: Option<String>,
+    show_event_log: bool,
+    event_log_kinds: Vec<EventKind>,
+    event_log_filter: String,
+    event_log_status: Option<String>,
+    timeline_view: TimelineView,
+    workload: Workload,
+    affinity: AffinityConfig,
+    affinity_text: String,
+    affinity_status: Option<String>,
 }
 
 impl App {
@@ -34,14 +98,661 @@ impl App {
             counter: Default::default(),
             background_task_spawn_num: 1,
             foreground_tasks_started: false,
+            next_foreground_id: 0,
+            launch_kind: AppKind::Demo,
             bench_result: std::sync::Arc::new(std::sync::Mutex::new(None)),
             bench_running: std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false)),
+            bench_control: Default::default(),
+            io_latency_ms: 10,
+            bench_history: Arc::new(Mutex::new(bench_history::load(Path::new(HISTORY_FILE)))),
+            show_bench_history: false,
+            proc_stats: ProcStats::default(),
+            show_proc_stats: false,
+            show_thread_inspector: false,
+            show_timeline: false,
+            work_stealing: true,
+            show_task_placement: false,
+            runtime_stats: RuntimeStats::new(tokio::runtime::Handle::current()),
+            show_runtime_metrics: false,
+            trace_status: None,
+            show_event_log: false,
+            event_log_kinds: EventKind::ALL.to_vec(),
+            event_log_filter: String::new(),
+            event_log_status: None,
+            timeline_view: TimelineView::default(),
+            workload: Workload::Trivial,
+            affinity: AffinityConfig::default(),
+            affinity_text: String::new(),
+            affinity_status: None,
+        }
+    }
+
+    /// What the kernel sees of the process, read from /proc
+    fn show_proc_stats(&mut self, ctx: &egui::Context) {
+        if !self.show_proc_stats {
+            return;
+        }
+        let stats = &mut self.proc_stats;
+        egui::Window::new("Kernel Statistics")
+            .open(&mut self.show_proc_stats)
+            .show(ctx, |ui| {
+                let snapshot = match stats.poll() {
+                    Ok(snapshot) => snapshot,
+                    Err(e) => {
+                        ui.label(e);
+                        return;
+                    }
+                };
+                ui.label(format!("Kernel threads: {}", snapshot.threads));
+                ui.label(format!(
+                    "Context switches: {} voluntary, {} involuntary",
+                    snapshot.voluntary_switches, snapshot.involuntary_switches
+                ));
+                ui.label(format!(
+                    "Process CPU: {:.1}% ({:.1?} total)",
+                    snapshot.cpu_percent, snapshot.cpu_time
+                ));
+                let states: Vec<String> = snapshot
+                    .state_counts()
+                    .iter()
+                    .map(|(state, n)| format!("{state}: {n}"))
+                    .collect();
+                ui.label(format!("Thread states: {}", states.join(", ")));
+                ui.separator();
+
+                ui.monospace(format!(
+                    "{:>8} {:<16} {:>5} {:>7} {:>10}",
+                    "TID", "Name", "State", "CPU%", "CPU time"
+                ));
+                let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+                egui::ScrollArea::vertical().show_rows(
+                    ui,
+                    row_height,
+                    snapshot.thread_stats.len(),
+                    |ui, range| {
+                        for stat in &snapshot.thread_stats[range] {
+                            ui.monospace(format!(
+                                "{:>8} {:<16} {:>5} {:>7.1} {:>10.2?}",
+                                stat.tid, stat.name, stat.state, stat.cpu_percent, stat.cpu_time
+                            ));
+                        }
+                    },
+                );
+            });
+        ctx.request_repaint_after(REFRESH_INTERVAL);
+    }
+
+    /// Every kernel thread grouped by the role it was named after, to compare how
+    /// many kernel threads each model needs for the same tasks
+    fn show_thread_inspector(&mut self, ctx: &egui::Context) {
+        if !self.show_thread_inspector {
+            return;
         }
+        let tasks = self.model.foreground_tasks().len() + self.model.num_background_tasks();
+        let kind = self.model.get_kind();
+        let stats = &mut self.proc_stats;
+        egui::Window::new("Thread Inspector")
+            .open(&mut self.show_thread_inspector)
+            .show(ctx, |ui| {
+                let snapshot = match stats.poll() {
+                    Ok(snapshot) => snapshot,
+                    Err(e) => {
+                        ui.label(e);
+                        return;
+                    }
+                };
+                let mut threads: Vec<(ThreadRole, &ThreadStat)> = snapshot
+                    .thread_stats
+                    .iter()
+                    .map(|stat| (ThreadRole::from_name(&stat.name), stat))
+                    .collect();
+                threads.sort_by_key(|(role, stat)| (*role, stat.tid));
+                let count = |role| threads.iter().filter(|(r, _)| *r == role).count();
+
+                let kernel_threads = match kind {
+                    ThreadModelKind::ManyToOne => 1,
+                    ThreadModelKind::OneToOne => {
+                        count(ThreadRole::Foreground) + count(ThreadRole::Background)
+                    }
+                    ThreadModelKind::ManyToMany => count(ThreadRole::TokioWorker),
+                };
+                ui.label(format!(
+                    "{kind}: {tasks} task(s) on {kernel_threads} kernel thread(s)"
+                ));
+                ui.separator();
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for role in [
+                        ThreadRole::Ui,
+                        ThreadRole::Foreground,
+                        ThreadRole::Background,
+                        ThreadRole::TokioWorker,
+                        ThreadRole::TokioBlocking,
+                        ThreadRole::Bench,
+                        ThreadRole::Other,
+                    ] {
+                        let n = count(role);
+                        if n == 0 {
+                            continue;
+                        }
+                        ui.collapsing(format!("{role} ({n})"), |ui| {
+                            ui.label(role.description());
+                            ui.monospace(format!(
+                                "{:>8} {:<16} {:>5} {:>4} {:>7}",
+                                "TID", "Name", "State", "CPU", "CPU%"
+                            ));
+                            for (_, stat) in threads.iter().filter(|(r, _)| *r == role) {
+                                ui.monospace(format!(
+                                    "{:>8} {:<16} {:>5} {:>4} {:>7.1}",
+                                    stat.tid,
+                                    stat.name,
+                                    stat.state,
+                                    stat.processor,
+                                    stat.cpu_percent
+                                ));
+                            }
+                        });
+                    }
+                });
+            });
+        ctx.request_repaint_after(REFRESH_INTERVAL);
+    }
+
+    /// Which task ran on which kernel thread and when. Tasks only record while this is open
+    fn show_timeline(&mut self, ctx: &egui::Context) {
+        timeline::set_enabled(self.show_timeline);
+        if !self.show_timeline {
+            return;
+        }
+        let view = &mut self.timeline_view;
+        egui::Window::new("Execution Timeline")
+            .open(&mut self.show_timeline)
+            .default_width(800.0)
+            .show(ctx, |ui| view.show(ui));
+        ctx.request_repaint();
+    }
+
+    fn log_model_switch(&self) {
+        let kind = self.model.get_kind();
+        info!(model = %kind, work_stealing = self.work_stealing, "model switched");
+        let message = if kind == ThreadModelKind::ManyToMany && !self.work_stealing {
+            format!("Switched to {kind} without work stealing")
+        } else {
+            format!("Switched to {kind}")
+        };
+        event_log::log(EventKind::Model, message);
+    }
+
+    fn launch_foreground_app(&mut self, kind: AppKind) {
+        let id = self.next_foreground_id;
+        self.next_foreground_id += 1;
+        self.model.create_foreground_task(id, kind.create(id));
+    }
+
+    /// Spawns the requested number of background tasks, stopping at the first failure
+    /// since the next attempts would hit the same thread limit
+    fn spawn_background_tasks(&mut self) {
+        let kind = self.model.get_kind();
+        let mut spawned = 0;
+        let mut error = None;
+        for _ in 0..self.background_task_spawn_num {
+            match self.model.create_background_task(self.counter.clone()) {
+                Ok(()) => spawned += 1,
+                Err(e) => {
+                    error = Some(e);
+                    break;
+                }
+            }
+        }
+        event_log::log(
+            EventKind::Task,
+            format!("{kind}: {spawned} background task(s) spawned"),
+        );
+        if let Some(e) = error {
+            event_log::log(
+                EventKind::Thread,
+                format!(
+                    "{kind}: failed to spawn {} more background task(s): {e}",
+                    self.background_task_spawn_num as usize - spawned
+                ),
+            );
+        }
+    }
+
+    /// Starts and closes instances of the interactive apps on the current model
+    fn show_app_launcher(&mut self, ui: &mut egui::Ui) {
+        let ids = self.model.foreground_tasks();
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("launch kind")
+                .selected_text(self.launch_kind.to_string())
+                .show_ui(ui, |ui| {
+                    for kind in AppKind::ALL {
+                        ui.selectable_value(&mut self.launch_kind, kind, kind.to_string());
+                    }
+                });
+            if ui.button("Launch app").clicked() {
+                self.launch_foreground_app(self.launch_kind);
+            }
+            ui.label(format!("Foreground tasks: {}", ids.len()));
+        });
+        ui.horizontal_wrapped(|ui| {
+            for id in ids {
+                if ui.button(format!("Close fg-{id}")).clicked() {
+                    self.model.close_foreground_task(id);
+                }
+            }
+        });
+    }
+
+    fn many_to_many_model(&self) -> Box<dyn ThreadModel> {
+        migration::reset();
+        if self.work_stealing {
+            Box::new(ManyToManyModel::new())
+        } else {
+            Box::new(ManyToManyModel::without_work_stealing())
+        }
+    }
+
+    /// Which tokio worker each Many-to-Many background task was polled on, and how often it moved
+    fn show_task_placement(&mut self, ctx: &egui::Context) {
+        migration::set_enabled(self.show_task_placement);
+        if !self.show_task_placement {
+            return;
+        }
+        let work_stealing = self.work_stealing;
+        egui::Window::new("Task Placement")
+            .open(&mut self.show_task_placement)
+            .show(ctx, |ui| {
+                let placement = migration::snapshot();
+                ui.horizontal(|ui| {
+                    ui.label(format!(
+                        "{} task(s), {} migration(s)",
+                        placement.tasks.len(),
+                        placement.total_migrations()
+                    ));
+                    if ui.button("Reset").clicked() {
+                        migration::reset();
+                    }
+                });
+                if work_stealing {
+                    //Steal counts are per worker of the shared runtime, not per task
+                    let metrics = tokio::runtime::Handle::current().metrics();
+                    let steals: Vec<String> = (0..metrics.num_workers())
+                        .map(|i| {
+                            let name = placement
+                                .workers
+                                .iter()
+                                .find(|w| Some(w.thread) == metrics.worker_thread_id(i))
+                                .map_or_else(|| format!("worker {i}"), |w| w.name.clone());
+                            format!("{name}: {}", metrics.worker_steal_count(i))
+                        })
+                        .collect();
+                    ui.label(format!("Tasks stolen: {}", steals.join(", ")));
+                } else {
+                    ui.label(
+                        "One runtime per worker: tasks never leave the worker they were spawned on",
+                    );
+                }
+                ui.separator();
+
+                let cell = vec2(44.0, 18.0);
+                let worker_polls = placement.worker_polls();
+                ui.horizontal(|ui| {
+                    ui.add_sized(vec2(60.0, cell.y), egui::Label::new("Task"));
+                    for worker in placement.workers.iter() {
+                        let short = worker.name.rsplit('-').next().unwrap_or(&worker.name);
+                        ui.add_sized(cell, egui::Label::new(short))
+                            .on_hover_text(&worker.name);
+                    }
+                    ui.label("Moves");
+                });
+                egui::ScrollArea::vertical().show_rows(
+                    ui,
+                    cell.y,
+                    placement.tasks.len(),
+                    |ui, range| {
+                        for task in &placement.tasks[range] {
+                            let max = task.polls.iter().copied().max().unwrap_or(1).max(1);
+                            ui.horizontal(|ui| {
+                                ui.add_sized(vec2(60.0, cell.y), egui::Label::new(&*task.task));
+                                for worker in 0..placement.workers.len() {
+                                    let polls = task.polls.get(worker).copied().unwrap_or(0);
+                                    let heat = polls as f32 / max as f32;
+                                    let (rect, _) = ui.allocate_exact_size(cell, Sense::hover());
+                                    ui.painter().rect_filled(
+                                        rect.shrink(1.0),
+                                        0.0,
+                                        Color32::from_rgb(40, 40, 40)
+                                            .lerp_to_gamma(Color32::from_rgb(230, 120, 30), heat),
+                                    );
+                                    ui.painter().text(
+                                        rect.center(),
+                                        Align2::CENTER_CENTER,
+                                        polls,
+                                        FontId::monospace(10.0),
+                                        Color32::WHITE,
+                                    );
+                                }
+                                ui.label(task.migrations.to_string());
+                            });
+                        }
+                    },
+                );
+                ui.separator();
+                let totals: Vec<String> = worker_polls.iter().map(u64::to_string).collect();
+                ui.label(format!("Polls per worker: {}", totals.join(", ")));
+            });
+        ctx.request_repaint_after(REFRESH_INTERVAL);
+    }
+
+    /// What the executor behind Many-to-Many is doing
+    fn show_runtime_metrics(&mut self, ctx: &egui::Context) {
+        if !self.show_runtime_metrics {
+            return;
+        }
+        let work_stealing = self.work_stealing;
+        let stats = &mut self.runtime_stats;
+        egui::Window::new("Tokio Runtime Metrics")
+            .open(&mut self.show_runtime_metrics)
+            .show(ctx, |ui| {
+                if !work_stealing {
+                    ui.label("Many-to-Many currently runs on per-worker runtimes, these are the shared runtime's metrics");
+                }
+                let snapshot = stats.poll();
+                ui.label(format!(
+                    "Workers: {}, alive tasks: {}, spawned so far: {}",
+                    snapshot.workers.len(),
+                    snapshot.alive_tasks,
+                    snapshot.spawned_tasks
+                ));
+                ui.label(format!("Global queue depth: {}", snapshot.global_queue_depth));
+                ui.label(format!(
+                    "Blocking pool: {} thread(s), {} idle, {} queued",
+                    snapshot.blocking_threads,
+                    snapshot.idle_blocking_threads,
+                    snapshot.blocking_queue_depth
+                ));
+                ui.separator();
+
+                egui::Grid::new("runtime_workers")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for header in ["Worker", "State", "Busy", "Polls", "Polls/s", "Local queue", "Steals", "Parks"] {
+                            ui.strong(header);
+                        }
+                        ui.end_row();
+                        for (i, worker) in snapshot.workers.iter().enumerate() {
+                            ui.label(i.to_string());
+                            ui.label(if worker.active { "active" } else { "parked" });
+                            ui.add(
+                                ProgressBar::new(worker.busy_ratio as f32)
+                                    .desired_width(100.0)
+                                    .text(format!("{:.0}%", worker.busy_ratio * 100.0)),
+                            );
+                            ui.label(worker.polls.to_string());
+                            ui.label(format!("{:.0}", worker.poll_rate));
+                            ui.label(worker.local_queue_depth.to_string());
+                            ui.label(worker.steals.to_string());
+                            ui.label(worker.parks.to_string());
+                            ui.end_row();
+                        }
+                    });
+            });
+        ctx.request_repaint_after(REFRESH_INTERVAL);
+    }
+
+    fn show_event_log(&mut self, ctx: &egui::Context) {
+        if !self.show_event_log {
+            return;
+        }
+        let kinds = &mut self.event_log_kinds;
+        let filter = &mut self.event_log_filter;
+        let status = &mut self.event_log_status;
+        egui::Window::new("Event Log")
+            .open(&mut self.show_event_log)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    for kind in EventKind::ALL {
+                        let mut shown = kinds.contains(&kind);
+                        if ui.checkbox(&mut shown, kind.to_string()).changed() {
+                            if shown {
+                                kinds.push(kind);
+                            } else {
+                                kinds.retain(|k| *k != kind);
+                            }
+                        }
+                    }
+                });
+                let entries = event_log::entries(kinds, filter);
+                ui.horizontal(|ui| {
+                    ui.label("Filter:");
+                    ui.text_edit_singleline(filter);
+                    if ui.button("Clear").clicked() {
+                        event_log::clear();
+                    }
+                    if ui.button("Export").clicked() {
+                        let timestamp = SystemTime::now()
+                            .duration_since(UNIX_EPOCH)
+                            .map(|d| d.as_secs())
+                            .unwrap_or_default();
+                        let path = format!("event_log-{timestamp}.txt");
+                        *status = Some(match event_log::export(Path::new(&path), &entries) {
+                            Ok(()) => format!("Wrote {} entries to {path}", entries.len()),
+                            Err(e) => format!("Failed to write {path}: {e}"),
+                        });
+                    }
+                });
+                if let Some(status) = status {
+                    ui.label(status.as_str());
+                }
+                ui.separator();
+
+                let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+                egui::ScrollArea::vertical()
+                    .stick_to_bottom(true)
+                    .show_rows(ui, row_height, entries.len(), |ui, range| {
+                        for entry in &entries[range] {
+                            ui.monospace(entry.to_string());
+                        }
+                    });
+            });
+    }
+
+    /// Records the session with `tracing` and exports it for Perfetto
+    fn show_trace_controls(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if trace::is_recording() {
+                if ui.button("Stop trace").clicked() {
+                    trace::stop_recording();
+                }
+            } else if ui.button("Record trace").clicked() {
+                trace::start_recording();
+                self.trace_status = None;
+            }
+            let (events, dropped) = trace::event_count();
+            if dropped > 0 {
+                ui.label(format!("{events} events, {dropped} dropped"));
+            } else {
+                ui.label(format!("{events} events"));
+            }
+            if ui
+                .add_enabled(
+                    !trace::is_recording() && events > 0,
+                    Button::new("Export trace"),
+                )
+                .clicked()
+            {
+                let timestamp = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or_default();
+                let path = format!("trace-{timestamp}.json");
+                self.trace_status = Some(match trace::export(Path::new(&path)) {
+                    Ok(()) => format!("Wrote {path}, open it in ui.perfetto.dev"),
+                    Err(e) => format!("Failed to write {path}: {e}"),
+                });
+            }
+        });
+        if let Some(status) = &self.trace_status {
+            ui.label(status);
+        }
+    }
+
+    /// Picks the per-message work of the overhead and channel benchmarks
+    fn show_workload(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_label("Workload")
+                .selected_text(self.workload.to_string())
+                .show_ui(ui, |ui| {
+                    let choices = [
+                        ("Trivial", Workload::Trivial),
+                        ("CPU", Workload::Cpu { nanos: 1000 }),
+                        ("Memory", Workload::Memory { kib: 64 }),
+                        ("Sleep", Workload::Sleep { micros: 100 }),
+                    ];
+                    for (label, choice) in choices {
+                        let selected = std::mem::discriminant(&self.workload)
+                            == std::mem::discriminant(&choice);
+                        if ui.selectable_label(selected, label).clicked() && !selected {
+                            self.workload = choice;
+                        }
+                    }
+                });
+            match &mut self.workload {
+                Workload::Trivial => {}
+                Workload::Cpu { nanos } => {
+                    ui.add(DragValue::new(nanos).suffix(" ns"));
+                }
+                Workload::Memory { kib } => {
+                    ui.add(
+                        DragValue::new(kib)
+                            .range(1..=MAX_TOTAL_MEMORY_KIB)
+                            .suffix(" KiB"),
+                    );
+                }
+                Workload::Sleep { micros } => {
+                    ui.add(DragValue::new(micros).suffix(" us"));
+                }
+            }
+        });
+    }
+
+    fn show_affinity(&mut self, ui: &mut egui::Ui) {
+        ui.label(format!(
+            "CPUs the process started with: {:?}",
+            crate::impls::affinity::startup_cpus()
+        ));
+        ui.horizontal(|ui| {
+            ui.label("CPUs: ");
+            if ui
+                .add(
+                    egui::TextEdit::singleline(&mut self.affinity_text)
+                        .hint_text("all, or e.g. 0-3,6"),
+                )
+                .changed()
+            {
+                match AffinityConfig::parse_cpu_list(&self.affinity_text) {
+                    Ok(cpus) => {
+                        self.affinity.cpus = cpus;
+                        self.affinity_status = None;
+                    }
+                    Err(e) => self.affinity_status = Some(e),
+                }
+            }
+        });
+        ui.checkbox(
+            &mut self.affinity.pin_threads,
+            "Pin each thread to its own core",
+        );
+        ui.label(format!("Benchmarks use: {}", self.affinity));
+        if ui.button("Apply to process").clicked() {
+            self.affinity_status = Some(match self.affinity.apply_to_process() {
+                Ok(()) => format!("Process restricted to {}", self.affinity),
+                Err(e) => format!("Failed to set affinity: {e}"),
+            });
+        }
+        if let Some(status) = &self.affinity_status {
+            ui.label(status);
+        }
+    }
+
+    /// Runs a benchmark on its own thread, then shows its result and records it in the history file.
+    /// Does nothing if another benchmark is still running, since the two would skew each other
+    fn spawn_benchmark(
+        &self,
+        name: &'static str,
+        run: impl FnOnce(&Arc<BenchControl>) -> Result<Option<BenchReport>, String> + Send + 'static,
+    ) {
+        if self.bench_running.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        event_log::log(EventKind::Benchmark, format!("{name} benchmark started"));
+        let bench_result = self.bench_result.clone();
+        let bench_running = self.bench_running.clone();
+        let bench_history = self.bench_history.clone();
+        let bench_control = self.bench_control.clone();
+        bench_control.reset();
+        spawn_named("bench-runner".to_string(), move || {
+            let _done = BenchDone {
+                name,
+                running: bench_running,
+                result: bench_result.clone(),
+            };
+            match run(&bench_control) {
+                Ok(Some(report)) => {
+                    let record = BenchRecord::new(&report);
+                    if let Err(e) = bench_history::append(Path::new(HISTORY_FILE), &record) {
+                        eprintln!("failed to write {HISTORY_FILE}: {e}");
+                    }
+                    bench_history.lock().unwrap().push(record);
+                    *bench_result.lock().unwrap() = Some(report.text);
+                    event_log::log(EventKind::Benchmark, format!("{name} benchmark finished"));
+                }
+                Ok(None) => {
+                    *bench_result.lock().unwrap() = Some("Benchmark cancelled".to_string());
+                    event_log::log(EventKind::Benchmark, format!("{name} benchmark cancelled"));
+                }
+                Err(e) => {
+                    *bench_result.lock().unwrap() = Some(format!("Benchmark not run: {e}"));
+                    event_log::log(
+                        EventKind::Benchmark,
+                        format!("{name} benchmark not run: {e}"),
+                    );
+                }
+            }
+        });
+    }
+
+    fn show_bench_history(&mut self, ctx: &egui::Context) {
+        egui::Window::new("Benchmark History")
+            .open(&mut self.show_bench_history)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for record in self.bench_history.lock().unwrap().iter().rev() {
+                        ui.collapsing(
+                            format!(
+                                "{time} {benchmark} ({params}) @ {rev}",
+                                time = record.formatted_time(),
+                                benchmark = record.benchmark,
+                                params = record.params,
+                                rev = record.git_rev,
+                            ),
+                            |ui| {
+                                ui.label(format!("CPU: {}", record.cpu));
+                                for (model, per_op) in record.results.iter() {
+                                    ui.label(format!("{model}: {:.1}ns/op", per_op * 1e9));
+                                }
+                            },
+                        );
+                    }
+                });
+            });
     }
 }
 
 impl eframe::App for App {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        let _frame = info_span!("frame").entered();
         ctx.set_pixels_per_point(1.3);
         let mut current_model = self.model.get_kind();
         CentralPanel::default().show(ctx, |_| {
@@ -57,7 +768,9 @@ impl eframe::App for App {
                         .changed()
                     {
                         self.model = Box::new(ManyToOneModel::default());
+                        self.log_model_switch();
                         self.foreground_tasks_started = false;
+                        self.next_foreground_id = 0;
                         return;
                     };
                     if ui
@@ -69,8 +782,10 @@ impl eframe::App for App {
                         .changed()
                     {
                         self.model = Box::new(OneToOneModel::default());
+                        self.log_model_switch();
 
                         self.foreground_tasks_started = false;
+                        self.next_foreground_id = 0;
                         return;
                     }
                     if ui
@@ -81,22 +796,37 @@ impl eframe::App for App {
                         )
                         .changed()
                     {
-                        self.model = Box::new(ManyToManyModel::default());
+                        self.model = self.many_to_many_model();
+                        self.log_model_switch();
 
                         self.foreground_tasks_started = false;
+                        self.next_foreground_id = 0;
+                        return;
+                    }
+                    if self.model.get_kind() == ThreadModelKind::ManyToMany
+                        && ui
+                            .checkbox(
+                                &mut self.work_stealing,
+                                "Work stealing between tokio workers",
+                            )
+                            .changed()
+                    {
+                        self.model = self.many_to_many_model();
+                        self.log_model_switch();
+                        self.foreground_tasks_started = false;
+                        self.next_foreground_id = 0;
                         return;
                     }
 
                     if !self.foreground_tasks_started {
-                        self.model.create_foreground_task();
+                        self.launch_foreground_app(AppKind::Demo);
                         self.foreground_tasks_started = true;
                     }
+                    self.show_app_launcher(ui);
                     ui.horizontal(|ui| {
                         ui.add(DragValue::new(&mut self.background_task_spawn_num));
                         if ui.button("Spawn n background tasks").clicked() {
-                            for _ in 0..self.background_task_spawn_num {
-                                self.model.create_background_task(self.counter.clone());
-                            }
+                            self.spawn_background_tasks();
                         }
                     });
                     if self.model.get_kind() == ThreadModelKind::ManyToMany
@@ -110,21 +840,105 @@ impl eframe::App for App {
                     ));
 
                     ui.separator();
-                    if ui.button("Run overhead benchmark").clicked() {
+                    let bench_running = self.bench_running.load(Ordering::Relaxed);
+                    self.show_workload(ui);
+                    if ui
+                        .add_enabled(!bench_running, Button::new("Run overhead benchmark"))
+                        .clicked()
+                    {
                         let n = 1000;
                         let iterations = 5000usize;
-                        let bench_result = self.bench_result.clone();
-                        let bench_running = self.bench_running.clone();
-                        bench_running.store(true, Ordering::Relaxed);
-                        std::thread::spawn(move || {
-                            let out = crate::impls::bench::run_benchmarks(n, iterations);
-                            *bench_result.lock().unwrap() = Some(out);
-                            bench_running.store(false, Ordering::Relaxed);
+                        let workload = self.workload;
+                        let affinity = self.affinity.clone();
+                        self.spawn_benchmark("overhead", move |control| {
+                            crate::impls::bench::run_benchmarks(
+                                n, iterations, workload, control, &affinity,
+                            )
                         });
                     }
 
-                    if self.bench_running.load(Ordering::Relaxed) {
-                        ui.label("Benchmark running...");
+                    if ui
+                        .add_enabled(!bench_running, Button::new("Run ping-pong benchmark"))
+                        .clicked()
+                    {
+                        let round_trips = 100_000;
+                        let affinity = self.affinity.clone();
+                        self.spawn_benchmark("ping-pong", move |control| {
+                            Ok(crate::impls::bench::run_ping_pong(
+                                round_trips,
+                                control,
+                                &affinity,
+                            ))
+                        });
+                    }
+
+                    if ui
+                        .add_enabled(
+                            !bench_running,
+                            Button::new("Run channel comparison benchmark"),
+                        )
+                        .clicked()
+                    {
+                        let n = 100;
+                        let iterations = 1000;
+                        let workload = self.workload;
+                        let affinity = self.affinity.clone();
+                        self.spawn_benchmark("channels", move |control| {
+                            crate::impls::bench_channels::run_channel_benchmarks(
+                                n, iterations, workload, control, &affinity,
+                            )
+                        });
+                    }
+
+                    ui.horizontal(|ui| {
+                        ui.add(DragValue::new(&mut self.io_latency_ms).suffix(" ms"));
+                        if ui
+                            .add_enabled(!bench_running, Button::new("Run blocking IO benchmark"))
+                            .clicked()
+                        {
+                            let n = 50;
+                            let ops_per_worker = 5;
+                            let latency = Duration::from_millis(self.io_latency_ms);
+                            let affinity = self.affinity.clone();
+                            self.spawn_benchmark("blocking-io", move |control| {
+                                Ok(crate::impls::bench_io::run_io_benchmarks(
+                                    n,
+                                    ops_per_worker,
+                                    latency,
+                                    control,
+                                    &affinity,
+                                ))
+                            });
+                        }
+                    });
+
+                    ui.checkbox(&mut self.show_bench_history, "Show benchmark history");
+                    ui.checkbox(&mut self.show_proc_stats, "Show kernel statistics");
+                    ui.checkbox(&mut self.show_thread_inspector, "Show thread inspector");
+                    ui.checkbox(&mut self.show_timeline, "Show execution timeline");
+                    ui.checkbox(&mut self.show_task_placement, "Show task placement");
+                    ui.checkbox(&mut self.show_runtime_metrics, "Show tokio runtime metrics");
+                    ui.checkbox(&mut self.show_event_log, "Show event log");
+                    self.show_trace_controls(ui);
+
+                    ui.collapsing("CPU affinity", |ui| self.show_affinity(ui));
+
+                    if bench_running {
+                        let (model, progress, total) = self.bench_control.current();
+                        ui.horizontal(|ui| {
+                            ui.label(format!("Benchmark running: {model}"));
+                            if ui.button("Cancel").clicked() {
+                                self.bench_control.cancel();
+                            }
+                        });
+                        ui.add(
+                            ProgressBar::new(progress as f32 / total.max(1) as f32)
+                                .text(format!("{progress}/{total}")),
+                        );
+                        for line in self.bench_control.finished() {
+                            ui.label(line);
+                        }
+                        ctx.request_repaint();
                     } else if let Some(res) = self.bench_result.lock().unwrap().as_ref() {
                         ui.label("Benchmark result:");
                         ui.collapsing("Details", |ui| {
@@ -143,6 +957,13 @@ impl eframe::App for App {
                     ui.add(ProgressBar::new(progress));
                 });
 
+            self.show_bench_history(ctx);
+            self.show_proc_stats(ctx);
+            self.show_thread_inspector(ctx);
+            self.show_timeline(ctx);
+            self.show_task_placement(ctx);
+            self.show_runtime_metrics(ctx);
+            self.show_event_log(ctx);
             self.model.run_interactive(ctx);
             self.model.join_interactive();
         });
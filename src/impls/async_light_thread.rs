@@ -1,4 +1,5 @@
 use std::{
+    future::Future,
     sync::{
         Arc,
         atomic::{AtomicBool, AtomicU64, Ordering},
@@ -6,118 +7,147 @@ use std::{
     time::Duration,
 };
 
-use egui::{Button, Context, DragValue, ImageSource};
+use egui::Context;
 use pollster::FutureExt;
 use rand::Rng;
-use rfd::{AsyncFileDialog, FileHandle};
 use tokio::{
+    runtime::Handle,
     spawn,
-    sync::mpsc::{Receiver, Sender, channel},
+    sync::{
+        mpsc::{Receiver, Sender, channel},
+        oneshot,
+    },
     task::JoinHandle,
     time::sleep,
 };
+use tracing::{Instrument, info, info_span};
 
 use crate::impls::{
-    DEFAULT_IMAGE,
-    app::{DEFAULT_AGE, DEFAULT_NAME, DEFAULT_TEXT},
-    load_image,
-    thread_model::{ThreadModel, ThreadModelKind},
+    affinity::pin_model_thread,
+    event_log::{self, EventKind},
+    foreground::{Blocker, ForegroundApp, FrameDone},
+    migration,
+    thread_model::{TaskIds, ThreadModel, ThreadModelKind},
+    thread_names::{TOKIO_BLOCKING, spawn_named},
+    timeline::Traced,
 };
 
-pub struct ForegroundGreenThread {
-    image: ImageSource<'static>,
-    loader_thread: Option<JoinHandle<Option<FileHandle>>>,
-    text_buffer: String,
-    form_name: String,
-    form_number: u32,
+/// A single-threaded runtime per worker thread. Unlike the shared runtime, whose
+/// workers steal queued tasks from each other, a task never leaves the worker it was spawned on
+pub struct LocalWorkers {
+    handles: Vec<Handle>,
+    next: usize,
+    shutdown: Vec<oneshot::Sender<()>>,
+    threads: Vec<std::thread::JoinHandle<()>>,
 }
 
-impl ForegroundGreenThread {
-    fn new(image: ImageSource<'static>) -> Self {
-        Self {
-            image,
-            loader_thread: None,
-            text_buffer: DEFAULT_TEXT.to_string(),
-            form_name: DEFAULT_NAME.to_string(),
-            form_number: DEFAULT_AGE,
+impl LocalWorkers {
+    pub fn new(n_workers: usize) -> Self {
+        let mut workers = Self {
+            handles: Vec::with_capacity(n_workers),
+            next: 0,
+            shutdown: Vec::with_capacity(n_workers),
+            threads: Vec::with_capacity(n_workers),
+        };
+        for i in 0..n_workers {
+            let rt = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .thread_name(TOKIO_BLOCKING)
+                .build()
+                .expect("tokio runtime");
+            let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+            workers.handles.push(rt.handle().clone());
+            workers.shutdown.push(shutdown_tx);
+            workers
+                .threads
+                .push(spawn_named(format!("tokio-local-{i}"), move || {
+                    pin_model_thread();
+                    let _ = rt.block_on(shutdown_rx);
+                }));
         }
+        workers
     }
 
-    async fn show(&mut self, ctx: &egui::Context) {
-        if let Some(handle) = self.loader_thread.take_if(|handle| handle.is_finished())
-            && let Ok(result) = handle.await
-            && let Some(path) = result
-        {
-            self.image = load_image(path.path(), ctx);
-        }
-
-        egui::Window::new("Image Viewer").show(ctx, |ui| {
-            ui.image(self.image.clone());
-            if ui
-                .add_enabled(self.loader_thread.is_none(), Button::new("Load Image"))
-                .clicked()
-            {
-                let dialogue = AsyncFileDialog::new();
-                let handle = spawn(dialogue.pick_file());
-                self.loader_thread = Some(handle);
-            }
-        });
+    /// Spawns on the workers round robin
+    fn spawn<F>(&mut self, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let handle = &self.handles[self.next % self.handles.len()];
+        self.next += 1;
+        handle.spawn(future)
+    }
+}
 
-        egui::Window::new("Text Editor").show(ctx, |ui| {
-            ui.text_edit_multiline(&mut self.text_buffer);
-        });
-        egui::Window::new("Form").show(ctx, |ui| {
-            ui.horizontal(|ui| {
-                ui.label("Name: ");
-                ui.text_edit_singleline(&mut self.form_name);
-            });
-
-            ui.horizontal(|ui| {
-                ui.label("Age: ");
-                ui.add(DragValue::new(&mut self.form_number));
-            });
-        });
+impl std::ops::Drop for LocalWorkers {
+    fn drop(&mut self) {
+        self.shutdown.clear();
+        for handle in self.threads.drain(..) {
+            let _ = handle.join();
+        }
     }
 }
 
-pub fn foreground_green_thread(on_done_tx: Sender<()>) -> (JoinHandle<()>, Sender<Context>) {
-    let image = DEFAULT_IMAGE;
+/// Hosts a foreground app as a task, whose blocking work goes to tokio's blocking pool
+pub fn foreground_green_thread(
+    label: Arc<str>,
+    app: Box<dyn ForegroundApp>,
+    on_done_tx: Sender<()>,
+) -> (Traced<impl Future<Output = ()>>, Sender<Context>) {
     let (show_tx, show_rc) = channel(1);
-    let handle = tokio::spawn(inner(image, show_rc, on_done_tx));
-    (handle, show_tx)
+    let span = info_span!("task", task = %label);
+    let task = Traced::new(label, inner(app, show_rc, on_done_tx).instrument(span));
+    (task, show_tx)
 }
 
 async fn inner(
-    image: ImageSource<'static>,
+    mut app: Box<dyn ForegroundApp>,
     mut show_rc: Receiver<Context>,
     on_done_tx: Sender<()>,
 ) {
-    let mut state = ForegroundGreenThread::new(image);
     while let Some(ctx) = show_rc.recv().await {
-        state.show(&ctx).await;
-        let _ = on_done_tx.send(()).await;
+        let _done = FrameDone(|| {
+            let _ = on_done_tx.try_send(());
+        });
+        app.show(&ctx, Blocker::Task);
     }
 }
 
 pub fn background_green_thread(
+    label: Arc<str>,
     counter: Arc<AtomicU64>,
     finished: Arc<AtomicBool>,
-) -> JoinHandle<()> {
-    spawn(inner_background(counter, finished))
+) -> Traced<impl Future<Output = ()>> {
+    let span = info_span!("task", task = %label);
+    Traced::new(
+        label.clone(),
+        inner_background(label, counter, finished).instrument(span),
+    )
 }
 
-pub fn background_evil_thread(finished: Arc<AtomicBool>) -> JoinHandle<()> {
-    spawn(inner_evil(finished))
+/// The evil task's blocking sleep happens inside a poll, so on the timeline it shows
+/// up as one long run span that keeps a worker from running anything else
+pub fn background_evil_thread(
+    label: Arc<str>,
+    finished: Arc<AtomicBool>,
+) -> Traced<impl Future<Output = ()>> {
+    let span = info_span!("task", task = %label);
+    Traced::new(label, inner_evil(finished).instrument(span))
 }
 
-async fn inner_background(counter: Arc<AtomicU64>, finished: Arc<AtomicBool>) {
+async fn inner_background(label: Arc<str>, counter: Arc<AtomicU64>, finished: Arc<AtomicBool>) {
     while !finished.load(Ordering::Relaxed) {
+        //The sleep below is the only await, so every iteration is exactly one poll
+        migration::record_poll(&label);
         let duration = {
             let mut rng = rand::rng();
 
             rng.random_range(0..1000)
         };
         counter.fetch_add(1, Ordering::Relaxed);
+        //Awaiting yields the worker, so the task is off-thread until the timer fires
+        info!(ms = duration, "sleep");
         sleep(Duration::from_millis(duration)).await;
     }
 }
@@ -131,15 +161,21 @@ async fn inner_evil(finished: Arc<AtomicBool>) {
         };
         //This actually blocks the thread rather than cooperatively yielding execution
         //If all the kernel threads block, execution cannot continue
-        std::thread::sleep(Duration::from_millis(duration));
+        info_span!("blocking sleep", ms = duration)
+            .in_scope(|| std::thread::sleep(Duration::from_millis(duration)));
     }
 }
 pub struct ManyToManyModel {
-    foreground_tasks: Vec<(JoinHandle<()>, Sender<Context>)>,
-    background_tasks: Vec<JoinHandle<()>>,
+    foreground_tasks: Vec<(usize, JoinHandle<()>, Sender<Context>)>,
+    background_tasks: Vec<(Arc<str>, JoinHandle<()>)>,
+    background_ids: TaskIds,
     on_done_tx: Sender<()>,
     on_done_rx: Receiver<()>,
+    /// Foreground tasks that took this frame, and so will report it done
+    frames_sent: usize,
     finished: Arc<AtomicBool>,
+    /// `None` schedules on the shared work stealing runtime
+    local_workers: Option<LocalWorkers>,
 }
 
 impl ManyToManyModel {
@@ -148,9 +184,31 @@ impl ManyToManyModel {
         Self {
             foreground_tasks: Vec::new(),
             background_tasks: Vec::new(),
+            background_ids: TaskIds::default(),
             on_done_tx,
             on_done_rx,
+            frames_sent: 0,
             finished: Default::default(),
+            local_workers: None,
+        }
+    }
+
+    /// Runs tasks on one runtime per worker instead, with as many workers as the shared runtime
+    pub fn without_work_stealing() -> Self {
+        let n_workers = Handle::current().metrics().num_workers();
+        let mut model = Self::new();
+        model.local_workers = Some(LocalWorkers::new(n_workers));
+        model
+    }
+
+    fn spawn<F>(&mut self, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        match self.local_workers.as_mut() {
+            Some(workers) => workers.spawn(future),
+            None => spawn(future),
         }
     }
 }
@@ -162,14 +220,46 @@ impl Default for ManyToManyModel {
 }
 
 impl ThreadModel for ManyToManyModel {
-    fn create_foreground_task(&mut self) {
-        self.foreground_tasks
-            .push(foreground_green_thread(self.on_done_tx.clone()));
+    fn create_foreground_task(&mut self, id: usize, app: Box<dyn ForegroundApp>) {
+        let label: Arc<str> = format!("fg-{id}").into();
+        info!(task = %label, "spawn");
+        event_log::log(
+            EventKind::Task,
+            format!("Many to Many: foreground task {label} spawned"),
+        );
+        let (task, show_tx) = foreground_green_thread(label, app, self.on_done_tx.clone());
+        let handle = self.spawn(task);
+        self.foreground_tasks.push((id, handle, show_tx));
+    }
+
+    fn close_foreground_task(&mut self, id: usize) {
+        let Some(i) = self.foreground_tasks.iter().position(|(n, _, _)| *n == id) else {
+            return;
+        };
+        //Closing the frame channel ends the task once it next waits for a frame
+        let (_, handle, show_tx) = self.foreground_tasks.remove(i);
+        std::mem::drop(show_tx);
+        let message = match handle.block_on() {
+            Ok(()) => format!("Many to Many: foreground task fg-{id} closed"),
+            Err(e) => format!("Many to Many: foreground task fg-{id} ended with {e}"),
+        };
+        event_log::log(EventKind::Task, message);
     }
 
-    fn create_background_task(&mut self, counter: Arc<AtomicU64>) {
-        self.background_tasks
-            .push(background_green_thread(counter, self.finished.clone()));
+    fn foreground_tasks(&self) -> Vec<usize> {
+        self.foreground_tasks.iter().map(|(id, _, _)| *id).collect()
+    }
+
+    fn create_background_task(&mut self, counter: Arc<AtomicU64>) -> std::io::Result<()> {
+        let label = self.background_ids.next_label("bg");
+        info!(task = %label, "spawn");
+        let handle = self.spawn(background_green_thread(
+            label.clone(),
+            counter,
+            self.finished.clone(),
+        ));
+        self.background_tasks.push((label, handle));
+        Ok(())
     }
 
     fn num_background_tasks(&self) -> usize {
@@ -177,14 +267,40 @@ impl ThreadModel for ManyToManyModel {
     }
 
     fn run_interactive(&mut self, ctx: &Context) {
-        for (_, sender) in self.foreground_tasks.iter() {
-            pollster::block_on(sender.send(ctx.clone())).unwrap();
+        self.frames_sent = 0;
+        let mut dead = Vec::new();
+        for (id, _, sender) in self.foreground_tasks.iter() {
+            match pollster::block_on(sender.send(ctx.clone())) {
+                Ok(()) => self.frames_sent += 1,
+                //The task is gone, which only happens when its app panicked
+                Err(_) => dead.push(*id),
+            }
+        }
+        for id in dead {
+            self.close_foreground_task(id);
         }
     }
 
     fn join_interactive(&mut self) {
-        for _ in self.foreground_tasks.iter() {
-            pollster::block_on(self.on_done_rx.recv()).unwrap();
+        for _ in 0..self.frames_sent {
+            //The model holds a sender itself, so this never sees a closed channel
+            let _ = pollster::block_on(self.on_done_rx.recv());
+        }
+        //Background tasks only return early by panicking
+        let (ended, running) = self
+            .background_tasks
+            .drain(..)
+            .partition::<Vec<_>, _>(|(_, handle)| handle.is_finished());
+        self.background_tasks = running;
+        for (label, handle) in ended {
+            if let Err(e) = handle.block_on()
+                && e.is_panic()
+            {
+                event_log::log(
+                    EventKind::Task,
+                    format!("Many to Many: task {label} panicked, its worker carries on"),
+                );
+            }
         }
     }
 
@@ -193,20 +309,36 @@ impl ThreadModel for ManyToManyModel {
     }
 
     fn create_evil_task(&mut self) {
-        self.background_tasks
-            .push(background_evil_thread(self.finished.clone()));
+        let label = self.background_ids.next_label("evil");
+        info!(task = %label, "spawn");
+        event_log::log(
+            EventKind::Task,
+            format!("Many to Many: evil task {label} started, it blocks whichever worker runs it"),
+        );
+        let handle = self.spawn(background_evil_thread(label.clone(), self.finished.clone()));
+        self.background_tasks.push((label, handle));
     }
 }
 
 impl std::ops::Drop for ManyToManyModel {
     fn drop(&mut self) {
         self.finished.store(true, Ordering::Relaxed);
-        for (handle, show_tx) in self.foreground_tasks.drain(..) {
+        for (id, handle, show_tx) in self.foreground_tasks.drain(..) {
             std::mem::drop(show_tx);
-            handle.block_on().unwrap();
+            if let Err(e) = handle.block_on() {
+                event_log::log(
+                    EventKind::Task,
+                    format!("Many to Many: foreground task fg-{id} ended with {e}"),
+                );
+            }
         }
-        for handle in self.background_tasks.drain(..) {
-            handle.block_on().unwrap();
+        for (label, handle) in self.background_tasks.drain(..) {
+            if let Err(e) = handle.block_on() {
+                event_log::log(
+                    EventKind::Task,
+                    format!("Many to Many: task {label} ended with {e}"),
+                );
+            }
         }
     }
 }
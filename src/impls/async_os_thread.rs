@@ -1,119 +1,92 @@
 use crate::impls::{
-    DEFAULT_IMAGE,
-    app::{DEFAULT_AGE, DEFAULT_NAME, DEFAULT_TEXT},
-    thread_model::{ThreadModel, ThreadModelKind},
+    affinity::pin_model_thread,
+    event_log::{self, EventKind},
+    foreground::{Blocker, ForegroundApp, FrameDone},
+    thread_model::{TaskIds, ThreadModel, ThreadModelKind},
+    thread_names::try_spawn_named,
+    timeline::{self, SpanKind},
 };
 use std::{
-    env::current_dir,
-    path::PathBuf,
     sync::{
         Arc,
         atomic::{AtomicBool, AtomicU64, Ordering},
         mpsc::{Receiver, SyncSender, sync_channel},
     },
-    thread::{JoinHandle, sleep, spawn},
-    time::Duration,
+    thread::{JoinHandle, sleep},
+    time::{Duration, Instant},
 };
 
-use egui::{Button, Context, DragValue, ImageSource};
+use egui::Context;
 use rand::Rng;
+use tracing::{info, info_span};
 
-use crate::impls::load_image;
-
-pub struct OsForegroundTask {
-    image: ImageSource<'static>,
-    loader_thread: Option<JoinHandle<Option<PathBuf>>>,
-    text_buffer: String,
-    form_name: String,
-    form_number: u32,
-}
-
-impl OsForegroundTask {
-    fn new(image: ImageSource<'static>) -> Self {
-        Self {
-            image,
-            loader_thread: None,
-            text_buffer: DEFAULT_TEXT.to_string(),
-            form_name: DEFAULT_NAME.to_string(),
-            form_number: DEFAULT_AGE,
-        }
-    }
-
-    fn show(&mut self, ctx: &egui::Context) {
-        egui::Window::new("Image viewer").show(ctx, |ui| {
-            ui.image(self.image.clone());
-            if let Some(handle) = self.loader_thread.take_if(|handle| handle.is_finished())
-                && let Ok(result) = handle.join()
-                && let Some(path) = result
-            {
-                self.image = load_image(&path, ctx);
-            }
-
-            if ui
-                .add_enabled(self.loader_thread.is_none(), Button::new("Load Image"))
-                .clicked()
-            {
-                let dialogue = rfd::FileDialog::new().set_directory(current_dir().unwrap());
-                let handle = spawn(|| dialogue.pick_file());
-                self.loader_thread = Some(handle);
-            }
-        });
-
-        egui::Window::new("Text Editor").show(ctx, |ui| {
-            ui.text_edit_multiline(&mut self.text_buffer);
-        });
-        egui::Window::new("Form").show(ctx, |ui| {
-            ui.horizontal(|ui| {
-                ui.label("Name: ");
-                ui.text_edit_singleline(&mut self.form_name);
-            });
-
-            ui.horizontal(|ui| {
-                ui.label("Age: ");
-                ui.add(DragValue::new(&mut self.form_number));
-            });
-        });
-    }
-}
-
+/// Hosts a foreground app on a thread of its own, so it can block without stalling the UI
 pub fn os_foreground(
-    thread_nr: usize,
+    id: usize,
+    mut app: Box<dyn ForegroundApp>,
     on_done_tx: SyncSender<()>,
-) -> (JoinHandle<()>, SyncSender<Context>) {
-    let image = DEFAULT_IMAGE;
+) -> std::io::Result<(JoinHandle<()>, SyncSender<Context>)> {
     let (show_tx, show_rc) = sync_channel(0);
-    let handle = std::thread::Builder::new()
-        .name(format!("Worker {thread_nr}"))
-        .spawn(move || {
-            let mut state = OsForegroundTask::new(image);
-            while let Ok(ctx) = show_rc.recv() {
-                state.show(&ctx);
+    let label: Arc<str> = format!("fg-{id}").into();
+    info!(task = %label, "spawn");
+    let handle = try_spawn_named(label.to_string(), move || {
+        pin_model_thread();
+        loop {
+            //Waiting for the next frame
+            let start = Instant::now();
+            let Ok(ctx) = show_rc.recv() else {
+                break;
+            };
+            timeline::record(&label, SpanKind::Block, start);
+
+            let _done = FrameDone(|| {
                 let _ = on_done_tx.send(());
-            }
-        })
-        .expect("failed to spawn thread");
-    (handle, show_tx)
+            });
+            let _span = info_span!("task", task = %label).entered();
+            let start = Instant::now();
+            app.show(&ctx, Blocker::Thread);
+            timeline::record(&label, SpanKind::Run, start);
+        }
+    })?;
+    Ok((handle, show_tx))
 }
 
-pub fn os_background(counter: Arc<AtomicU64>, finished: Arc<AtomicBool>) -> JoinHandle<()> {
-    std::thread::spawn(move || {
+pub fn os_background(
+    thread_nr: usize,
+    counter: Arc<AtomicU64>,
+    finished: Arc<AtomicBool>,
+) -> std::io::Result<JoinHandle<()>> {
+    let label: Arc<str> = format!("bg-{thread_nr}").into();
+    info!(task = %label, "spawn");
+    try_spawn_named(label.to_string(), move || {
+        pin_model_thread();
         while !finished.load(Ordering::Relaxed) {
+            let _span = info_span!("task", task = %label).entered();
+            let start = Instant::now();
             let duration = {
                 let mut rng = rand::rng();
 
                 rng.random_range(0..1000)
             };
             counter.fetch_add(1, Ordering::Relaxed);
-            sleep(Duration::from_millis(duration))
+            timeline::record(&label, SpanKind::Run, start);
+
+            //Only this task's own kernel thread blocks
+            let start = Instant::now();
+            info_span!("sleep", ms = duration).in_scope(|| sleep(Duration::from_millis(duration)));
+            timeline::record(&label, SpanKind::Block, start);
         }
     })
 }
 
 pub struct OneToOneModel {
-    foreground_tasks: Vec<(JoinHandle<()>, SyncSender<egui::Context>)>,
+    foreground_tasks: Vec<(usize, JoinHandle<()>, SyncSender<egui::Context>)>,
     background_tasks: Vec<JoinHandle<()>>,
+    background_ids: TaskIds,
     on_done_tx: SyncSender<()>,
     on_done_rx: Receiver<()>,
+    /// Foreground tasks that took this frame, and so will report it done
+    frames_sent: usize,
     finished: Arc<AtomicBool>,
 }
 
@@ -125,7 +98,9 @@ impl OneToOneModel {
             foreground_tasks: Vec::new(),
             on_done_tx,
             on_done_rx: on_done_rc,
+            frames_sent: 0,
             background_tasks: Vec::new(),
+            background_ids: TaskIds::default(),
             finished: Default::default(),
         }
     }
@@ -136,15 +111,50 @@ impl ThreadModel for OneToOneModel {
         ThreadModelKind::OneToOne
     }
 
-    fn create_foreground_task(&mut self) {
-        let thread_nr = self.foreground_tasks.len();
-        self.foreground_tasks
-            .push(os_foreground(thread_nr, self.on_done_tx.clone()));
+    fn create_foreground_task(&mut self, id: usize, app: Box<dyn ForegroundApp>) {
+        match os_foreground(id, app, self.on_done_tx.clone()) {
+            Ok((handle, show_tx)) => {
+                self.foreground_tasks.push((id, handle, show_tx));
+                event_log::log(
+                    EventKind::Task,
+                    format!("One to One: foreground task fg-{id} started on its own thread"),
+                );
+            }
+            Err(e) => event_log::log(
+                EventKind::Thread,
+                format!("One to One: failed to spawn thread fg-{id}: {e}"),
+            ),
+        }
     }
 
-    fn create_background_task(&mut self, counter: Arc<AtomicU64>) {
-        self.background_tasks
-            .push(os_background(counter.clone(), self.finished.clone()));
+    fn close_foreground_task(&mut self, id: usize) {
+        let Some(i) = self.foreground_tasks.iter().position(|(n, _, _)| *n == id) else {
+            return;
+        };
+        //Hanging up the frame channel ends the thread's loop
+        let (_, handle, show_tx) = self.foreground_tasks.remove(i);
+        std::mem::drop(show_tx);
+        let message = match handle.join() {
+            Ok(()) => format!("One to One: foreground task fg-{id} closed, its thread exited"),
+            Err(_) => {
+                format!("One to One: foreground task fg-{id} panicked, only its own thread died")
+            }
+        };
+        event_log::log(EventKind::Task, message);
+    }
+
+    fn foreground_tasks(&self) -> Vec<usize> {
+        self.foreground_tasks.iter().map(|(id, _, _)| *id).collect()
+    }
+
+    fn create_background_task(&mut self, counter: Arc<AtomicU64>) -> std::io::Result<()> {
+        let handle = os_background(
+            self.background_ids.next_id(),
+            counter,
+            self.finished.clone(),
+        )?;
+        self.background_tasks.push(handle);
+        Ok(())
     }
 
     fn num_background_tasks(&self) -> usize {
@@ -152,15 +162,39 @@ impl ThreadModel for OneToOneModel {
     }
 
     fn run_interactive(&mut self, ctx: &Context) {
-        for (_, sender) in self.foreground_tasks.iter_mut() {
-            let _ = sender.send(ctx.clone());
+        self.frames_sent = 0;
+        let mut dead = Vec::new();
+        for (id, _, sender) in self.foreground_tasks.iter() {
+            match sender.send(ctx.clone()) {
+                Ok(()) => self.frames_sent += 1,
+                //The thread is gone, which only happens when its app panicked
+                Err(_) => dead.push(*id),
+            }
+        }
+        for id in dead {
+            self.close_foreground_task(id);
         }
     }
 
     fn join_interactive(&mut self) {
-        for _ in self.foreground_tasks.iter_mut() {
+        for _ in 0..self.frames_sent {
             let _ = self.on_done_rx.recv();
         }
+        //Background threads only return early by panicking
+        let (ended, running) = self
+            .background_tasks
+            .drain(..)
+            .partition::<Vec<_>, _>(|handle| handle.is_finished());
+        self.background_tasks = running;
+        for handle in ended {
+            let name = handle.thread().name().unwrap_or("unnamed").to_string();
+            if handle.join().is_err() {
+                event_log::log(
+                    EventKind::Task,
+                    format!("One to One: task {name} panicked, only its own thread died"),
+                );
+            }
+        }
     }
 
     fn create_evil_task(&mut self) {}
@@ -175,12 +209,22 @@ impl Default for OneToOneModel {
 impl std::ops::Drop for OneToOneModel {
     fn drop(&mut self) {
         self.finished.store(true, Ordering::Relaxed);
-        for (handle, show_tx) in self.foreground_tasks.drain(..) {
+        for (id, handle, show_tx) in self.foreground_tasks.drain(..) {
             std::mem::drop(show_tx);
-            handle.join().unwrap()
+            if handle.join().is_err() {
+                event_log::log(
+                    EventKind::Task,
+                    format!("One to One: foreground task fg-{id} had panicked"),
+                );
+            }
         }
         for handle in self.background_tasks.drain(..) {
-            handle.join().unwrap();
+            if handle.join().is_err() {
+                event_log::log(
+                    EventKind::Task,
+                    "One to One: a background task had panicked".to_string(),
+                );
+            }
         }
     }
 }
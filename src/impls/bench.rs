@@ -1,23 +1,244 @@
 use std::sync::{
-    Arc,
-    atomic::{AtomicU64, Ordering},
+    Arc, Mutex,
+    atomic::{AtomicBool, AtomicU8, AtomicU64, AtomicUsize, Ordering},
+};
+use std::{
+    fmt::Display,
+    hint::black_box,
+    time::{Duration, Instant},
 };
-use std::time::Instant;
 
 use tokio::runtime::Runtime;
 
-fn do_work(counter: &Arc<AtomicU64>) {
-    counter.fetch_add(1, Ordering::Relaxed);
-    let mut s: u64 = 0;
-    for _ in 0..100 {
-        s = s.wrapping_add(1);
+use crate::impls::{affinity::AffinityConfig, thread_names::spawn_named};
+
+/// Outcome of a benchmark run. `results` holds seconds per operation for each
+/// model, which is what gets persisted and compared between runs
+pub struct BenchReport {
+    pub benchmark: &'static str,
+    pub params: String,
+    pub results: Vec<(String, f64)>,
+    pub text: String,
+}
+
+/// Shared between a running benchmark and the UI. The benchmark reports which
+/// model it is on and how far along it is, publishes each model's result as
+/// soon as it is done, and stops early once `cancel` has been called
+#[derive(Default)]
+pub struct BenchControl {
+    cancelled: AtomicBool,
+    progress: AtomicUsize,
+    total: AtomicUsize,
+    current: Mutex<String>,
+    finished: Mutex<Vec<String>>,
+}
+
+impl BenchControl {
+    pub fn reset(&self) {
+        self.cancelled.store(false, Ordering::Relaxed);
+        self.progress.store(0, Ordering::Relaxed);
+        self.total.store(0, Ordering::Relaxed);
+        self.current.lock().unwrap().clear();
+        self.finished.lock().unwrap().clear();
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// The model currently being measured, and how many of its steps are done out of the total
+    pub fn current(&self) -> (String, usize, usize) {
+        (
+            self.current.lock().unwrap().clone(),
+            self.progress.load(Ordering::Relaxed),
+            self.total.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Result lines of the models that have completed so far
+    pub fn finished(&self) -> Vec<String> {
+        self.finished.lock().unwrap().clone()
+    }
+
+    pub(crate) fn start_model(&self, name: &str, total: usize) {
+        *self.current.lock().unwrap() = name.to_string();
+        self.progress.store(0, Ordering::Relaxed);
+        self.total.store(total, Ordering::Relaxed);
+    }
+
+    pub(crate) fn advance(&self) {
+        self.progress.fetch_add(1, Ordering::Relaxed);
     }
-    let _ = s;
+
+    /// Publishes `done` as the progress and checks for cancellation, but only on every
+    /// `every`th step so the timed loops stay clear of the cache line the UI reads.
+    /// Returns whether to stop
+    pub(crate) fn checkpoint(&self, done: usize, every: usize) -> bool {
+        if !done.is_multiple_of(every) {
+            return false;
+        }
+        self.progress.store(done, Ordering::Relaxed);
+        self.is_cancelled()
+    }
+
+    pub(crate) fn finish_model(&self, line: String) {
+        self.progress
+            .store(self.total.load(Ordering::Relaxed), Ordering::Relaxed);
+        self.finished.lock().unwrap().push(line);
+    }
+}
+
+/// Roughly how many operations a timed loop runs between two checkpoints
+const OPS_PER_CHECKPOINT: usize = 4096;
+
+/// How many iterations of `ops_per_iteration` operations fit between two checkpoints
+pub(crate) fn checkpoint_interval(ops_per_iteration: usize) -> usize {
+    (OPS_PER_CHECKPOINT / ops_per_iteration.max(1)).max(1)
+}
+
+/// Measures a single model, returning `None` if the run was cancelled
+pub(crate) type ModelBench<'a> = Box<dyn FnOnce() -> Option<Duration> + 'a>;
+
+/// Largest memory workload, summed over the buffers of every worker. The overhead
+/// benchmark allocates one buffer for each of its 1000 workers
+pub const MAX_TOTAL_MEMORY_KIB: usize = 512 * 1024;
+
+/// What a worker does with each message it receives
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Workload {
+    /// 100 wrapping adds, small enough that the results are pure synchronization overhead
+    Trivial,
+    /// Spin on the CPU for a fixed number of nanoseconds
+    Cpu { nanos: u64 },
+    /// Touch every cache line of a per-worker buffer of this many KiB
+    Memory { kib: usize },
+    /// Sleep for this many microseconds. Async workers await a tokio sleep, everyone else blocks
+    Sleep { micros: u64 },
 }
 
-fn bench_one_to_one(n_workers: usize, iterations: usize) -> std::time::Duration {
+impl Workload {
+    /// Parses `trivial`, `cpu:<nanos>`, `memory:<KiB>` or `sleep:<micros>`
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let (kind, amount) = spec.split_once(':').unwrap_or((spec, ""));
+        let amount = || {
+            amount
+                .parse::<u64>()
+                .map_err(|_| format!("'{spec}' needs a number after the colon"))
+        };
+        match kind {
+            "trivial" => Ok(Workload::Trivial),
+            "cpu" => Ok(Workload::Cpu { nanos: amount()? }),
+            "memory" => match amount()? {
+                kib @ 1.. if kib <= MAX_TOTAL_MEMORY_KIB as u64 => {
+                    Ok(Workload::Memory { kib: kib as usize })
+                }
+                _ => Err(format!(
+                    "'{spec}' needs between 1 and {MAX_TOTAL_MEMORY_KIB} KiB"
+                )),
+            },
+            "sleep" => Ok(Workload::Sleep { micros: amount()? }),
+            _ => Err(format!(
+                "unknown workload '{spec}', expected trivial, cpu:N, memory:N or sleep:N"
+            )),
+        }
+    }
+
+    /// Fails if `n_workers` buffers of the memory workload would exceed `MAX_TOTAL_MEMORY_KIB`
+    pub fn check(self, n_workers: usize) -> Result<(), String> {
+        match self {
+            Workload::Memory { kib } if kib.saturating_mul(n_workers) > MAX_TOTAL_MEMORY_KIB => {
+                Err(format!(
+                    "{n_workers} workers with {kib} KiB each exceed the {} MiB memory workload limit, use at most {} KiB",
+                    MAX_TOTAL_MEMORY_KIB / 1024,
+                    MAX_TOTAL_MEMORY_KIB / n_workers.max(1)
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Display for Workload {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Workload::Trivial => f.write_str("trivial"),
+            Workload::Cpu { nanos } => write!(f, "cpu {nanos}ns"),
+            Workload::Memory { kib } => write!(f, "memory {kib}KiB"),
+            Workload::Sleep { micros } => write!(f, "sleep {micros}us"),
+        }
+    }
+}
+
+/// A worker's private state for running its workload
+pub(crate) struct Work {
+    counter: Arc<AtomicU64>,
+    workload: Workload,
+    buffer: Vec<u8>,
+}
+
+impl Work {
+    pub(crate) fn new(counter: Arc<AtomicU64>, workload: Workload) -> Self {
+        let buffer = match workload {
+            Workload::Memory { kib } => vec![0; kib * 1024],
+            _ => Vec::new(),
+        };
+        Self {
+            counter,
+            workload,
+            buffer,
+        }
+    }
+
+    pub(crate) fn run(&mut self) {
+        self.counter.fetch_add(1, Ordering::Relaxed);
+        match self.workload {
+            Workload::Trivial => {
+                let mut s: u64 = 0;
+                for _ in 0..100 {
+                    s = s.wrapping_add(1);
+                }
+                black_box(s);
+            }
+            Workload::Cpu { nanos } => {
+                let start = Instant::now();
+                let mut s: u64 = 0;
+                while start.elapsed().as_nanos() < nanos as u128 {
+                    s = black_box(s.wrapping_add(1));
+                }
+            }
+            Workload::Memory { .. } => {
+                for byte in self.buffer.iter_mut().step_by(64) {
+                    *byte = byte.wrapping_add(1);
+                }
+                black_box(&self.buffer);
+            }
+            Workload::Sleep { micros } => std::thread::sleep(Duration::from_micros(micros)),
+        }
+    }
+
+    pub(crate) async fn run_async(&mut self) {
+        match self.workload {
+            Workload::Sleep { micros } => {
+                self.counter.fetch_add(1, Ordering::Relaxed);
+                tokio::time::sleep(Duration::from_micros(micros)).await;
+            }
+            _ => self.run(),
+        }
+    }
+}
+
+fn bench_one_to_one(
+    n_workers: usize,
+    iterations: usize,
+    workload: Workload,
+    control: &BenchControl,
+    affinity: &AffinityConfig,
+) -> Option<Duration> {
     use std::sync::mpsc::{SyncSender, sync_channel};
-    use std::thread;
 
     let counter = Arc::new(AtomicU64::new(0));
 
@@ -26,13 +247,15 @@ fn bench_one_to_one(n_workers: usize, iterations: usize) -> std::time::Duration
     let mut handles = Vec::with_capacity(n_workers);
     let mut senders: Vec<SyncSender<()>> = Vec::with_capacity(n_workers);
 
-    for _ in 0..n_workers {
+    for i in 0..n_workers {
         let (tx, rx) = sync_channel::<()>(0);
         let on_done_tx = on_done_tx.clone();
-        let counter = counter.clone();
-        let handle = thread::spawn(move || {
+        let mut work = Work::new(counter.clone(), workload);
+        let affinity = affinity.clone();
+        let handle = spawn_named(format!("bench-{i}"), move || {
+            affinity.pin_current_thread(i);
             while let Ok(()) = rx.recv() {
-                do_work(&counter);
+                work.run();
                 let _ = on_done_tx.send(());
             }
         });
@@ -41,7 +264,11 @@ fn bench_one_to_one(n_workers: usize, iterations: usize) -> std::time::Duration
     }
 
     let start = Instant::now();
-    for _ in 0..iterations {
+    let every = checkpoint_interval(n_workers);
+    for i in 0..iterations {
+        if control.checkpoint(i, every) {
+            break;
+        }
         for s in senders.iter() {
             let _ = s.send(());
         }
@@ -56,16 +283,20 @@ fn bench_one_to_one(n_workers: usize, iterations: usize) -> std::time::Duration
         let _ = h.join();
     }
 
-    dur
+    (!control.is_cancelled()).then_some(dur)
 }
 
-fn bench_many_to_many(n_workers: usize, iterations: usize) -> std::time::Duration {
+fn bench_async(
+    rt: &Runtime,
+    n_workers: usize,
+    iterations: usize,
+    workload: Workload,
+    control: &BenchControl,
+) -> Option<Duration> {
     use tokio::sync::mpsc::{Sender, channel};
 
     let counter = Arc::new(AtomicU64::new(0));
 
-    let rt = Runtime::new().expect("tokio runtime");
-
     rt.block_on(async {
         let (on_done_tx, mut on_done_rx) = channel::<()>(100_000);
         let mut senders: Vec<Sender<()>> = Vec::with_capacity(n_workers);
@@ -73,10 +304,10 @@ fn bench_many_to_many(n_workers: usize, iterations: usize) -> std::time::Duratio
         for _ in 0..n_workers {
             let (tx, mut rx) = channel::<()>(1);
             let on_done_tx = on_done_tx.clone();
-            let counter = counter.clone();
+            let mut work = Work::new(counter.clone(), workload);
             tokio::spawn(async move {
                 while let Some(()) = rx.recv().await {
-                    do_work(&counter);
+                    work.run_async().await;
                     let _ = on_done_tx.send(()).await;
                 }
             });
@@ -84,7 +315,11 @@ fn bench_many_to_many(n_workers: usize, iterations: usize) -> std::time::Duratio
         }
 
         let start = Instant::now();
-        for _ in 0..iterations {
+        let every = checkpoint_interval(n_workers);
+        for i in 0..iterations {
+            if control.checkpoint(i, every) {
+                break;
+            }
             for s in senders.iter_mut() {
                 let _ = s.send(()).await;
             }
@@ -95,45 +330,332 @@ fn bench_many_to_many(n_workers: usize, iterations: usize) -> std::time::Duratio
         let dur = start.elapsed();
 
         drop(senders);
-        dur
+        (!control.is_cancelled()).then_some(dur)
     })
 }
 
-fn bench_many_to_one(n_workers: usize, iterations: usize) -> std::time::Duration {
+fn bench_many_to_one(
+    n_workers: usize,
+    iterations: usize,
+    workload: Workload,
+    control: &BenchControl,
+) -> Option<Duration> {
     let counter = Arc::new(AtomicU64::new(0));
 
-    let mut tasks: Vec<Arc<AtomicU64>> = Vec::with_capacity(n_workers);
+    let mut tasks: Vec<Work> = Vec::with_capacity(n_workers);
     for _ in 0..n_workers {
-        tasks.push(counter.clone());
+        tasks.push(Work::new(counter.clone(), workload));
     }
 
     let start = Instant::now();
-    for _ in 0..iterations {
-        for t in tasks.iter() {
-            do_work(t);
-        }
-    }
-    start.elapsed()
-}
-
-pub fn run_benchmarks(n_workers: usize, iterations: usize) -> String {
-    let one_to_one = bench_one_to_one(n_workers, iterations);
-    let many_to_many = bench_many_to_many(n_workers, iterations);
-    let many_to_one = bench_many_to_one(n_workers, iterations);
-
-    let per_op_oto = one_to_one.as_secs_f64() / (n_workers as f64 * iterations as f64);
-    let per_op_mtm = many_to_many.as_secs_f64() / (n_workers as f64 * iterations as f64);
-    let per_op_mto = many_to_one.as_secs_f64() / (n_workers as f64 * iterations as f64);
-
-    format!(
-        "Benchmark results (workers = {n}, iters = {it})\n\nOne-to-One (OS threads): {oto:?} total, {per_oto:.9}s/op\nMany-to-Many (async tasks): {mtm:?} total, {per_mtm:.9}s/op\nMany-to-One (sequential): {mto:?} total, {per_mto:.9}s/op\n",
-        n = n_workers,
-        it = iterations,
-        oto = one_to_one,
-        per_oto = per_op_oto,
-        mtm = many_to_many,
-        per_mtm = per_op_mtm,
-        mto = many_to_one,
-        per_mto = per_op_mto,
-    )
+    let every = checkpoint_interval(n_workers);
+    for i in 0..iterations {
+        if control.checkpoint(i, every) {
+            return None;
+        }
+        for t in tasks.iter_mut() {
+            t.run();
+        }
+    }
+    Some(start.elapsed())
+}
+
+pub fn run_benchmarks(
+    n_workers: usize,
+    iterations: usize,
+    workload: Workload,
+    control: &BenchControl,
+    affinity: &AffinityConfig,
+) -> Result<Option<BenchReport>, String> {
+    workload.check(n_workers)?;
+    affinity.restrict_current_thread();
+    let multi_thread = affinity.multi_thread_runtime();
+    let current_thread = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("tokio runtime");
+
+    let models: [(&str, ModelBench<'_>); 4] = [
+        (
+            "One-to-One (OS threads)",
+            Box::new(|| bench_one_to_one(n_workers, iterations, workload, control, affinity)),
+        ),
+        (
+            "Many-to-Many (async tasks)",
+            Box::new(|| bench_async(&multi_thread, n_workers, iterations, workload, control)),
+        ),
+        (
+            "Many-to-One (current-thread runtime)",
+            Box::new(|| bench_async(&current_thread, n_workers, iterations, workload, control)),
+        ),
+        (
+            "Many-to-One (sequential)",
+            Box::new(|| bench_many_to_one(n_workers, iterations, workload, control)),
+        ),
+    ];
+
+    let ops = n_workers as f64 * iterations as f64;
+    let mut text = format!(
+        "Benchmark results (workers = {n_workers}, iters = {iterations}, workload = {workload})\n\n"
+    );
+    let mut results = Vec::with_capacity(models.len());
+    for (name, bench) in models {
+        control.start_model(name, iterations);
+        let Some(dur) = bench() else {
+            return Ok(None);
+        };
+        let per_op = dur.as_secs_f64() / ops;
+        let line = format!("{name}: {dur:?} total, {per_op:.9}s/op");
+        text.push_str(&line);
+        text.push('\n');
+        control.finish_model(line);
+        results.push((name.to_string(), per_op));
+    }
+    text.push_str("\nThe first three run the same channel protocol, so they compare scheduling costs directly.\nThe current-thread runtime is a true many-to-one executor: every task shares one kernel thread.\nThe sequential run calls the work inline with no message passing, so it is the lower bound\nfor the work itself rather than a comparable threading model.\n");
+
+    Ok(Some(BenchReport {
+        benchmark: "overhead",
+        params: format!(
+            "workers={n_workers} iters={iterations} workload={workload} affinity={affinity}"
+        ),
+        results,
+        text,
+    }))
+}
+
+fn bench_ping_pong_channel(
+    round_trips: usize,
+    control: &BenchControl,
+    affinity: &AffinityConfig,
+) -> Option<Duration> {
+    use std::sync::mpsc::sync_channel;
+
+    let (ping_tx, ping_rx) = sync_channel::<()>(0);
+    let (pong_tx, pong_rx) = sync_channel::<()>(0);
+
+    let affinity = affinity.clone();
+    let handle = spawn_named("bench-pong".to_string(), move || {
+        affinity.pin_current_thread(1);
+        while let Ok(()) = ping_rx.recv() {
+            let _ = pong_tx.send(());
+        }
+    });
+
+    let start = Instant::now();
+    let every = checkpoint_interval(1);
+    for i in 0..round_trips {
+        if control.checkpoint(i, every) {
+            break;
+        }
+        let _ = ping_tx.send(());
+        let _ = pong_rx.recv();
+    }
+    let dur = start.elapsed();
+
+    drop(ping_tx);
+    let _ = handle.join();
+    (!control.is_cancelled()).then_some(dur)
+}
+
+const PING: u8 = 0;
+const PONG: u8 = 1;
+const DONE: u8 = 2;
+
+fn bench_ping_pong_park(
+    round_trips: usize,
+    control: &BenchControl,
+    affinity: &AffinityConfig,
+) -> Option<Duration> {
+    use std::thread;
+
+    //The token is a single atomic, and the waiting side parks directly on it,
+    //so each switch is one futex wake plus one futex wait
+    let turn = Arc::new(AtomicU8::new(PING));
+    let main = thread::current();
+
+    let handle = {
+        let turn = turn.clone();
+        let affinity = affinity.clone();
+        spawn_named("bench-pong".to_string(), move || {
+            affinity.pin_current_thread(1);
+            loop {
+                match turn.load(Ordering::Acquire) {
+                    PONG => {
+                        turn.store(PING, Ordering::Release);
+                        main.unpark();
+                    }
+                    DONE => break,
+                    _ => thread::park(),
+                }
+            }
+        })
+    };
+    let peer = handle.thread().clone();
+
+    let start = Instant::now();
+    let every = checkpoint_interval(1);
+    for i in 0..round_trips {
+        if control.checkpoint(i, every) {
+            break;
+        }
+        turn.store(PONG, Ordering::Release);
+        peer.unpark();
+        while turn.load(Ordering::Acquire) != PING {
+            thread::park();
+        }
+    }
+    let dur = start.elapsed();
+
+    turn.store(DONE, Ordering::Release);
+    peer.unpark();
+    let _ = handle.join();
+    (!control.is_cancelled()).then_some(dur)
+}
+
+fn bench_ping_pong_tokio(
+    rt: &Runtime,
+    round_trips: usize,
+    control: &BenchControl,
+) -> Option<Duration> {
+    use tokio::sync::mpsc::channel;
+
+    rt.block_on(async {
+        let (ping_tx, mut ping_rx) = channel::<()>(1);
+        let (pong_tx, mut pong_rx) = channel::<()>(1);
+
+        let handle = tokio::spawn(async move {
+            while let Some(()) = ping_rx.recv().await {
+                let _ = pong_tx.send(()).await;
+            }
+        });
+
+        let start = Instant::now();
+        let every = checkpoint_interval(1);
+        for i in 0..round_trips {
+            if control.checkpoint(i, every) {
+                break;
+            }
+            let _ = ping_tx.send(()).await;
+            let _ = pong_rx.recv().await;
+        }
+        let dur = start.elapsed();
+
+        drop(ping_tx);
+        let _ = handle.await;
+        (!control.is_cancelled()).then_some(dur)
+    })
+}
+
+/// Yields back to whoever is polling the coroutines exactly once
+struct YieldNow(bool);
+
+impl std::future::Future for YieldNow {
+    type Output = ();
+
+    fn poll(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<()> {
+        if self.0 {
+            return std::task::Poll::Ready(());
+        }
+        self.0 = true;
+        cx.waker().wake_by_ref();
+        std::task::Poll::Pending
+    }
+}
+
+fn bench_ping_pong_coroutine(round_trips: usize, control: &BenchControl) -> Option<Duration> {
+    use std::cell::Cell;
+
+    //Two coroutines joined into one future and driven by pollster on the calling
+    //thread. There is no runtime, no queue and no kernel involvement in a switch
+    let turn = Cell::new(PING);
+
+    let ping = async {
+        let every = checkpoint_interval(1);
+        for i in 0..round_trips {
+            if control.checkpoint(i, every) {
+                break;
+            }
+            turn.set(PONG);
+            while turn.get() != PING {
+                YieldNow(false).await;
+            }
+        }
+        turn.set(DONE);
+    };
+    let pong = async {
+        loop {
+            match turn.get() {
+                PONG => turn.set(PING),
+                DONE => break,
+                _ => YieldNow(false).await,
+            }
+        }
+    };
+
+    let start = Instant::now();
+    pollster::block_on(async { tokio::join!(ping, pong) });
+    let dur = start.elapsed();
+    (!control.is_cancelled()).then_some(dur)
+}
+
+pub fn run_ping_pong(
+    round_trips: usize,
+    control: &BenchControl,
+    affinity: &AffinityConfig,
+) -> Option<BenchReport> {
+    //The calling thread is one side of every ping-pong, so it takes the first CPU
+    affinity.pin_current_thread(0);
+    let multi_thread = affinity.multi_thread_runtime();
+    let current_thread = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("tokio runtime");
+
+    let models: [(&str, ModelBench<'_>); 5] = [
+        (
+            "One-to-One (std channel)",
+            Box::new(|| bench_ping_pong_channel(round_trips, control, affinity)),
+        ),
+        (
+            "One-to-One (park/unpark)",
+            Box::new(|| bench_ping_pong_park(round_trips, control, affinity)),
+        ),
+        (
+            "Many-to-Many (tokio multi-thread)",
+            Box::new(|| bench_ping_pong_tokio(&multi_thread, round_trips, control)),
+        ),
+        (
+            "Many-to-One (tokio current-thread)",
+            Box::new(|| bench_ping_pong_tokio(&current_thread, round_trips, control)),
+        ),
+        (
+            "Many-to-One (coroutines)",
+            Box::new(|| bench_ping_pong_coroutine(round_trips, control)),
+        ),
+    ];
+
+    //Every round trip hands the token over twice
+    let switches = 2.0 * round_trips as f64;
+    let mut text = format!("Ping-pong results (round trips = {round_trips})\n\n");
+    let mut per_switch = Vec::with_capacity(models.len());
+    for (name, bench) in models {
+        control.start_model(name, round_trips);
+        let dur = bench()?;
+        let per_switch_ns = dur.as_secs_f64() * 1e9 / switches;
+        let line = format!("{name}: {dur:?} total, {per_switch_ns:.1}ns/switch");
+        text.push_str(&line);
+        text.push('\n');
+        control.finish_model(line);
+        per_switch.push((name.to_string(), dur.as_secs_f64() / switches));
+    }
+
+    Some(BenchReport {
+        benchmark: "ping-pong",
+        params: format!("round_trips={round_trips} affinity={affinity}"),
+        results: per_switch,
+        text,
+    })
 }
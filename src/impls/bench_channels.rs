@@ -0,0 +1,405 @@
+use std::{
+    fmt::Display,
+    sync::{
+        Arc, Condvar, Mutex,
+        atomic::AtomicU64,
+        mpsc::{self, sync_channel},
+    },
+    time::{Duration, Instant},
+};
+
+use tokio::{runtime::Runtime, sync::mpsc as tokio_mpsc};
+
+use crate::impls::{
+    affinity::AffinityConfig,
+    bench::{BenchControl, BenchReport, ModelBench, Work, Workload, checkpoint_interval},
+    thread_names::spawn_named,
+};
+
+const BOUNDED_CAPACITY: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelKind {
+    StdRendezvous,
+    StdBounded,
+    StdUnbounded,
+    TokioBounded,
+    TokioUnbounded,
+    CondvarQueue,
+}
+
+impl ChannelKind {
+    pub const ALL: [ChannelKind; 6] = [
+        ChannelKind::StdRendezvous,
+        ChannelKind::StdBounded,
+        ChannelKind::StdUnbounded,
+        ChannelKind::TokioBounded,
+        ChannelKind::TokioUnbounded,
+        ChannelKind::CondvarQueue,
+    ];
+
+    /// Only tokio channels can be awaited, the others would block the worker they run on
+    fn is_async(self) -> bool {
+        matches!(
+            self,
+            ChannelKind::TokioBounded | ChannelKind::TokioUnbounded
+        )
+    }
+}
+
+impl Display for ChannelKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            ChannelKind::StdRendezvous => "std rendezvous",
+            ChannelKind::StdBounded => "std bounded",
+            ChannelKind::StdUnbounded => "std unbounded",
+            ChannelKind::TokioBounded => "tokio bounded",
+            ChannelKind::TokioUnbounded => "tokio unbounded",
+            ChannelKind::CondvarQueue => "Mutex+Condvar queue",
+        };
+        f.write_str(str)
+    }
+}
+
+struct CondvarState {
+    items: usize,
+    senders: usize,
+}
+
+struct CondvarShared {
+    state: Mutex<CondvarState>,
+    ready: Condvar,
+}
+
+/// The simplest possible hand-written queue: a counter behind a mutex, and a
+/// condition variable the receiver sleeps on while it is zero
+struct CondvarSender(Arc<CondvarShared>);
+
+struct CondvarReceiver(Arc<CondvarShared>);
+
+impl Clone for CondvarSender {
+    fn clone(&self) -> Self {
+        self.0.state.lock().unwrap().senders += 1;
+        Self(self.0.clone())
+    }
+}
+
+impl Drop for CondvarSender {
+    fn drop(&mut self) {
+        let mut state = self.0.state.lock().unwrap();
+        state.senders -= 1;
+        if state.senders == 0 {
+            self.0.ready.notify_all();
+        }
+    }
+}
+
+impl CondvarSender {
+    fn send(&self) {
+        self.0.state.lock().unwrap().items += 1;
+        self.0.ready.notify_one();
+    }
+}
+
+impl CondvarReceiver {
+    fn recv(&self) -> bool {
+        let mut state = self.0.state.lock().unwrap();
+        loop {
+            if state.items > 0 {
+                state.items -= 1;
+                return true;
+            }
+            if state.senders == 0 {
+                return false;
+            }
+            state = self.0.ready.wait(state).unwrap();
+        }
+    }
+}
+
+#[derive(Clone)]
+enum Tx {
+    StdSync(mpsc::SyncSender<()>),
+    Std(mpsc::Sender<()>),
+    TokioBounded(tokio_mpsc::Sender<()>),
+    TokioUnbounded(tokio_mpsc::UnboundedSender<()>),
+    Condvar(CondvarSender),
+}
+
+enum Rx {
+    Std(mpsc::Receiver<()>),
+    TokioBounded(tokio_mpsc::Receiver<()>),
+    TokioUnbounded(tokio_mpsc::UnboundedReceiver<()>),
+    Condvar(CondvarReceiver),
+}
+
+fn channel(kind: ChannelKind) -> (Tx, Rx) {
+    match kind {
+        ChannelKind::StdRendezvous => {
+            let (tx, rx) = sync_channel(0);
+            (Tx::StdSync(tx), Rx::Std(rx))
+        }
+        ChannelKind::StdBounded => {
+            let (tx, rx) = sync_channel(BOUNDED_CAPACITY);
+            (Tx::StdSync(tx), Rx::Std(rx))
+        }
+        ChannelKind::StdUnbounded => {
+            let (tx, rx) = mpsc::channel();
+            (Tx::Std(tx), Rx::Std(rx))
+        }
+        ChannelKind::TokioBounded => {
+            let (tx, rx) = tokio_mpsc::channel(BOUNDED_CAPACITY);
+            (Tx::TokioBounded(tx), Rx::TokioBounded(rx))
+        }
+        ChannelKind::TokioUnbounded => {
+            let (tx, rx) = tokio_mpsc::unbounded_channel();
+            (Tx::TokioUnbounded(tx), Rx::TokioUnbounded(rx))
+        }
+        ChannelKind::CondvarQueue => {
+            let shared = Arc::new(CondvarShared {
+                state: Mutex::new(CondvarState {
+                    items: 0,
+                    senders: 1,
+                }),
+                ready: Condvar::new(),
+            });
+            (
+                Tx::Condvar(CondvarSender(shared.clone())),
+                Rx::Condvar(CondvarReceiver(shared)),
+            )
+        }
+    }
+}
+
+impl Tx {
+    /// Must not be called from inside a tokio runtime
+    fn send_blocking(&self) {
+        match self {
+            Tx::StdSync(tx) => {
+                let _ = tx.send(());
+            }
+            Tx::Std(tx) => {
+                let _ = tx.send(());
+            }
+            Tx::TokioBounded(tx) => {
+                let _ = tx.blocking_send(());
+            }
+            Tx::TokioUnbounded(tx) => {
+                let _ = tx.send(());
+            }
+            Tx::Condvar(tx) => tx.send(),
+        }
+    }
+
+    async fn send(&self) {
+        match self {
+            Tx::TokioBounded(tx) => {
+                let _ = tx.send(()).await;
+            }
+            Tx::TokioUnbounded(tx) => {
+                let _ = tx.send(());
+            }
+            _ => unreachable!("blocking channels are never awaited"),
+        }
+    }
+}
+
+impl Rx {
+    /// Must not be called from inside a tokio runtime
+    fn recv_blocking(&mut self) -> bool {
+        match self {
+            Rx::Std(rx) => rx.recv().is_ok(),
+            Rx::TokioBounded(rx) => rx.blocking_recv().is_some(),
+            Rx::TokioUnbounded(rx) => rx.blocking_recv().is_some(),
+            Rx::Condvar(rx) => rx.recv(),
+        }
+    }
+
+    async fn recv(&mut self) -> bool {
+        match self {
+            Rx::TokioBounded(rx) => rx.recv().await.is_some(),
+            Rx::TokioUnbounded(rx) => rx.recv().await.is_some(),
+            _ => unreachable!("blocking channels are never awaited"),
+        }
+    }
+}
+
+/// The broadcast/collect protocol of the overhead benchmark, with OS threads as workers
+fn bench_threads(
+    kind: ChannelKind,
+    n_workers: usize,
+    iterations: usize,
+    workload: Workload,
+    control: &BenchControl,
+    affinity: &AffinityConfig,
+) -> Option<Duration> {
+    let counter = Arc::new(AtomicU64::new(0));
+    let (on_done_tx, mut on_done_rx) = channel(kind);
+
+    let mut handles = Vec::with_capacity(n_workers);
+    let mut senders = Vec::with_capacity(n_workers);
+    for i in 0..n_workers {
+        let (tx, mut rx) = channel(kind);
+        let on_done_tx = on_done_tx.clone();
+        let mut work = Work::new(counter.clone(), workload);
+        let affinity = affinity.clone();
+        handles.push(spawn_named(format!("bench-{i}"), move || {
+            affinity.pin_current_thread(i);
+            while rx.recv_blocking() {
+                work.run();
+                on_done_tx.send_blocking();
+            }
+        }));
+        senders.push(tx);
+    }
+
+    let start = Instant::now();
+    let every = checkpoint_interval(n_workers);
+    for i in 0..iterations {
+        if control.checkpoint(i, every) {
+            break;
+        }
+        for s in senders.iter() {
+            s.send_blocking();
+        }
+        for _ in 0..n_workers {
+            on_done_rx.recv_blocking();
+        }
+    }
+    let dur = start.elapsed();
+
+    drop(senders);
+    for h in handles {
+        let _ = h.join();
+    }
+    (!control.is_cancelled()).then_some(dur)
+}
+
+/// The same protocol with tokio tasks as workers
+fn bench_tasks(
+    rt: &Runtime,
+    kind: ChannelKind,
+    n_workers: usize,
+    iterations: usize,
+    workload: Workload,
+    control: &BenchControl,
+) -> Option<Duration> {
+    let counter = Arc::new(AtomicU64::new(0));
+
+    rt.block_on(async {
+        let (on_done_tx, mut on_done_rx) = channel(kind);
+        let mut senders = Vec::with_capacity(n_workers);
+        for _ in 0..n_workers {
+            let (tx, mut rx) = channel(kind);
+            let on_done_tx = on_done_tx.clone();
+            let mut work = Work::new(counter.clone(), workload);
+            tokio::spawn(async move {
+                while rx.recv().await {
+                    work.run_async().await;
+                    on_done_tx.send().await;
+                }
+            });
+            senders.push(tx);
+        }
+
+        let start = Instant::now();
+        let every = checkpoint_interval(n_workers);
+        for i in 0..iterations {
+            if control.checkpoint(i, every) {
+                break;
+            }
+            for s in senders.iter() {
+                s.send().await;
+            }
+            for _ in 0..n_workers {
+                on_done_rx.recv().await;
+            }
+        }
+        let dur = start.elapsed();
+
+        drop(senders);
+        (!control.is_cancelled()).then_some(dur)
+    })
+}
+
+pub fn run_channel_benchmarks(
+    n_workers: usize,
+    iterations: usize,
+    workload: Workload,
+    control: &BenchControl,
+    affinity: &AffinityConfig,
+) -> Result<Option<BenchReport>, String> {
+    workload.check(n_workers)?;
+    affinity.restrict_current_thread();
+    let multi_thread = affinity.multi_thread_runtime();
+    let current_thread = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("tokio runtime");
+
+    let ops = n_workers as f64 * iterations as f64;
+    let mut text = format!(
+        "Channel comparison (workers = {n_workers}, iters = {iterations}, workload = {workload}, bounded capacity = {BOUNDED_CAPACITY})\n\n"
+    );
+    let mut results = Vec::new();
+    for kind in ChannelKind::ALL {
+        let mut cells: Vec<(&str, ModelBench<'_>)> = vec![(
+            "OS threads",
+            Box::new(move || {
+                bench_threads(kind, n_workers, iterations, workload, control, affinity)
+            }),
+        )];
+        if kind.is_async() {
+            cells.push((
+                "tokio multi-thread",
+                Box::new(|| {
+                    bench_tasks(
+                        &multi_thread,
+                        kind,
+                        n_workers,
+                        iterations,
+                        workload,
+                        control,
+                    )
+                }),
+            ));
+            cells.push((
+                "tokio current-thread",
+                Box::new(|| {
+                    bench_tasks(
+                        &current_thread,
+                        kind,
+                        n_workers,
+                        iterations,
+                        workload,
+                        control,
+                    )
+                }),
+            ));
+        }
+
+        for (scheduler, bench) in cells {
+            let name = format!("{kind} / {scheduler}");
+            control.start_model(&name, iterations);
+            let Some(dur) = bench() else {
+                return Ok(None);
+            };
+            let per_op = dur.as_secs_f64() / ops;
+            let line = format!("{name}: {dur:?} total, {:.1}ns/op", per_op * 1e9);
+            text.push_str(&line);
+            text.push('\n');
+            control.finish_model(line);
+            results.push((name, per_op));
+        }
+    }
+    text.push_str("\nComparing rows with the same scheduler isolates the cost of the channel.\nThe tokio channels also run on OS threads through their blocking API, so comparing\ntheir rows across schedulers isolates the cost of scheduling.\n");
+
+    Ok(Some(BenchReport {
+        benchmark: "channels",
+        params: format!(
+            "workers={n_workers} iters={iterations} workload={workload} affinity={affinity}"
+        ),
+        results,
+        text,
+    }))
+}
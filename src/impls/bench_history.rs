@@ -0,0 +1,400 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::Path,
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::impls::{
+    affinity::AffinityConfig,
+    bench::{BenchControl, BenchReport, Workload, run_benchmarks},
+};
+
+pub const HISTORY_FILE: &str = "bench_history.tsv";
+pub const BASELINE_FILE: &str = "bench_baseline.tsv";
+pub const DEFAULT_THRESHOLD: f64 = 10.0;
+
+/// A benchmark run as persisted to disk, one tab separated line per record:
+/// `timestamp  git_rev  cpu  benchmark  params  model=secs_per_op;model=secs_per_op...`
+#[derive(Debug, Clone)]
+pub struct BenchRecord {
+    pub timestamp: u64,
+    pub git_rev: String,
+    pub cpu: String,
+    pub benchmark: String,
+    pub params: String,
+    pub results: Vec<(String, f64)>,
+}
+
+impl BenchRecord {
+    pub fn new(report: &BenchReport) -> Self {
+        Self {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            git_rev: git_revision(),
+            cpu: host_cpu(),
+            benchmark: report.benchmark.to_string(),
+            params: report.params.clone(),
+            results: report.results.clone(),
+        }
+    }
+
+    fn to_line(&self) -> String {
+        let results = self
+            .results
+            .iter()
+            .map(|(model, per_op)| format!("{}={per_op:e}", model.replace([';', '='], " ")))
+            .collect::<Vec<_>>()
+            .join(";");
+        format!(
+            "{}\t{}\t{}\t{}\t{}\t{results}",
+            self.timestamp,
+            sanitize(&self.git_rev),
+            sanitize(&self.cpu),
+            sanitize(&self.benchmark),
+            sanitize(&self.params),
+        )
+    }
+
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.split('\t');
+        let timestamp = fields.next()?.parse().ok()?;
+        let git_rev = fields.next()?.to_string();
+        let cpu = fields.next()?.to_string();
+        let benchmark = fields.next()?.to_string();
+        let params = fields.next()?.to_string();
+        let results = fields
+            .next()?
+            .split(';')
+            .filter_map(|entry| {
+                let (model, per_op) = entry.split_once('=')?;
+                Some((model.to_string(), per_op.parse().ok()?))
+            })
+            .collect();
+        Some(Self {
+            timestamp,
+            git_rev,
+            cpu,
+            benchmark,
+            params,
+            results,
+        })
+    }
+
+    /// The timestamp as `YYYY-MM-DD HH:MM:SS UTC`
+    pub fn formatted_time(&self) -> String {
+        let days = (self.timestamp / 86400) as i64;
+        let secs = self.timestamp % 86400;
+        //Days since the epoch to a civil date, see http://howardhinnant.github.io/date_algorithms.html
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z.rem_euclid(146097);
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + i64::from(month <= 2);
+        format!(
+            "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02} UTC",
+            secs / 3600,
+            secs % 3600 / 60,
+            secs % 60
+        )
+    }
+
+    fn same_setup(&self, other: &BenchRecord) -> bool {
+        self.benchmark == other.benchmark && self.params == other.params
+    }
+}
+
+fn sanitize(field: &str) -> String {
+    field.replace(['\t', '\n'], " ")
+}
+
+pub fn git_revision() -> String {
+    Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|rev| rev.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+pub fn host_cpu() -> String {
+    let model = fs::read_to_string("/proc/cpuinfo")
+        .ok()
+        .and_then(|info| {
+            info.lines()
+                .find(|line| line.starts_with("model name"))
+                .and_then(|line| line.split_once(':'))
+                .map(|(_, name)| name.trim().to_string())
+        })
+        .unwrap_or_else(|| std::env::consts::ARCH.to_string());
+    let threads = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1);
+    format!("{model} ({threads} threads)")
+}
+
+pub fn load(path: &Path) -> Vec<BenchRecord> {
+    fs::read_to_string(path)
+        .map(|contents| contents.lines().filter_map(BenchRecord::parse).collect())
+        .unwrap_or_default()
+}
+
+pub fn append(path: &Path, record: &BenchRecord) -> std::io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", record.to_line())
+}
+
+/// Compares every model in `latest` against `baseline`. Returns a printable
+/// summary and whether any model got slower by more than `threshold` percent.
+/// A baseline that is not a positive time fails too, since nothing can be compared to it
+pub fn compare(baseline: &BenchRecord, latest: &BenchRecord, threshold: f64) -> (String, bool) {
+    let mut out = format!(
+        "{} ({}): baseline {} @ {} vs latest {} @ {}\n",
+        latest.benchmark,
+        latest.params,
+        baseline.git_rev,
+        baseline.formatted_time(),
+        latest.git_rev,
+        latest.formatted_time(),
+    );
+    let mut regressed = false;
+    for (model, per_op) in latest.results.iter() {
+        let Some((_, base)) = baseline.results.iter().find(|(m, _)| m == model) else {
+            out.push_str(&format!("  {model}: no baseline\n"));
+            continue;
+        };
+        //Also catches NaN, which would compare false against the threshold and pass
+        if !(base.is_finite() && *base > 0.0) {
+            regressed = true;
+            out.push_str(&format!("  {model}: INVALID BASELINE {base}\n"));
+            continue;
+        }
+        let change = (per_op - base) / base * 100.0;
+        let verdict = if change > threshold {
+            regressed = true;
+            "REGRESSION"
+        } else {
+            "ok"
+        };
+        out.push_str(&format!(
+            "  {model}: {base:.3e}s -> {per_op:.3e}s ({change:+.1}%) {verdict}\n"
+        ));
+    }
+    (out, regressed)
+}
+
+/// Handles the benchmark command line flags. Returns `None` when none were
+/// given and the GUI should start, otherwise the process exit code
+///
+/// `--bench-run`           run the overhead benchmark and append it to the history
+/// `--bench-set-baseline`  store the latest record of each benchmark as the baseline
+/// `--bench-compare`       compare the latest records against the baseline
+/// `--threshold <PCT>`     allowed slowdown for `--bench-compare`, default 10%
+/// `--cpus <LIST>`         CPUs `--bench-run` may use, e.g. `0-3,6`
+/// `--pin`                 pin each benchmark thread to its own CPU
+/// `--workload <SPEC>`     per-message work for `--bench-run`: trivial, cpu:<ns>, memory:<KiB> or sleep:<us>
+const USAGE: &str = "usage: [--bench-run] [--bench-set-baseline] [--bench-compare] [--threshold PCT] [--cpus LIST] [--pin] [--workload SPEC]";
+
+pub fn run_cli(args: &[String]) -> Option<i32> {
+    let mut run = false;
+    let mut set_baseline = false;
+    let mut compare_latest = false;
+    let mut threshold = DEFAULT_THRESHOLD;
+    let mut affinity = AffinityConfig::default();
+    let mut workload = Workload::Trivial;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--bench-run" => run = true,
+            "--bench-set-baseline" => set_baseline = true,
+            "--bench-compare" => compare_latest = true,
+            "--pin" => affinity.pin_threads = true,
+            "--workload" => match iter.next().map(|w| Workload::parse(w)) {
+                Some(Ok(w)) => workload = w,
+                Some(Err(e)) => {
+                    eprintln!("--workload: {e}");
+                    return Some(2);
+                }
+                None => {
+                    eprintln!("--workload expects trivial, cpu:N, memory:N or sleep:N");
+                    return Some(2);
+                }
+            },
+            "--cpus" => match iter.next().map(|l| AffinityConfig::parse_cpu_list(l)) {
+                Some(Ok(cpus)) => affinity.cpus = cpus,
+                Some(Err(e)) => {
+                    eprintln!("--cpus: {e}");
+                    return Some(2);
+                }
+                None => {
+                    eprintln!("--cpus expects a list like 0-3,6");
+                    return Some(2);
+                }
+            },
+            "--threshold" => match iter.next().and_then(|t| t.parse().ok()) {
+                Some(t) => threshold = t,
+                None => {
+                    eprintln!("--threshold expects a percentage");
+                    return Some(2);
+                }
+            },
+            //A mistyped flag must fail a CI job rather than open the GUI
+            unknown if unknown.starts_with('-') => {
+                eprintln!("unknown argument '{unknown}'\n{USAGE}");
+                return Some(2);
+            }
+            _ => {}
+        }
+    }
+    if !(run || set_baseline || compare_latest) {
+        return None;
+    }
+
+    if run {
+        let report = match run_benchmarks(1000, 5000, workload, &BenchControl::default(), &affinity)
+        {
+            Ok(report) => report.expect("nothing cancels a command line run"),
+            Err(e) => {
+                eprintln!("--workload: {e}");
+                return Some(2);
+            }
+        };
+        println!("{}", report.text);
+        if let Err(e) = append(Path::new(HISTORY_FILE), &BenchRecord::new(&report)) {
+            eprintln!("failed to write {HISTORY_FILE}: {e}");
+            return Some(2);
+        }
+    }
+
+    let history = load(Path::new(HISTORY_FILE));
+    let mut latest: Vec<&BenchRecord> = Vec::new();
+    for record in history.iter().rev() {
+        if !latest.iter().any(|r| r.same_setup(record)) {
+            latest.push(record);
+        }
+    }
+
+    if set_baseline {
+        let contents: String = latest.iter().map(|r| r.to_line() + "\n").collect();
+        if let Err(e) = fs::write(BASELINE_FILE, contents) {
+            eprintln!("failed to write {BASELINE_FILE}: {e}");
+            return Some(2);
+        }
+        println!("Stored {} record(s) as the baseline", latest.len());
+    }
+
+    if compare_latest {
+        let baseline = load(Path::new(BASELINE_FILE));
+        if baseline.is_empty() {
+            eprintln!("no baseline in {BASELINE_FILE}, run with --bench-set-baseline first");
+            return Some(2);
+        }
+        let mut regressed = false;
+        for base in baseline.iter() {
+            match latest.iter().find(|r| r.same_setup(base)) {
+                Some(record) => {
+                    let (summary, r) = compare(base, record, threshold);
+                    print!("{summary}");
+                    regressed |= r;
+                }
+                None => println!("{} ({}): no run recorded", base.benchmark, base.params),
+            }
+        }
+        if regressed {
+            println!("Regression above {threshold}% detected");
+            return Some(1);
+        }
+    }
+    Some(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(results: &[(&str, f64)]) -> BenchRecord {
+        BenchRecord {
+            timestamp: 1_700_000_000,
+            git_rev: "abc1234".to_string(),
+            cpu: "Test\tCPU (8 threads)".to_string(),
+            benchmark: "overhead".to_string(),
+            params: "workers=1000 iters=5000".to_string(),
+            results: results
+                .iter()
+                .map(|(model, per_op)| (model.to_string(), *per_op))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn line_round_trip() {
+        let original = record(&[("One-to-One (OS threads)", 1.25e-6), ("Many=;Many", 3e-7)]);
+        let parsed = BenchRecord::parse(&original.to_line()).unwrap();
+        assert_eq!(parsed.timestamp, original.timestamp);
+        assert_eq!(parsed.git_rev, original.git_rev);
+        assert_eq!(parsed.cpu, "Test CPU (8 threads)");
+        assert_eq!(parsed.benchmark, original.benchmark);
+        assert_eq!(parsed.params, original.params);
+        assert_eq!(
+            parsed.results,
+            vec![
+                ("One-to-One (OS threads)".to_string(), 1.25e-6),
+                ("Many  Many".to_string(), 3e-7),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_lines() {
+        assert!(BenchRecord::parse("").is_none());
+        assert!(BenchRecord::parse("1700000000\tabc\tcpu\toverhead").is_none());
+        assert!(BenchRecord::parse("soon\tabc\tcpu\toverhead\tparams\ta=1").is_none());
+    }
+
+    #[test]
+    fn regression_threshold() {
+        let baseline = record(&[("a", 100.0), ("b", 100.0)]);
+        let (_, regressed) = compare(&baseline, &record(&[("a", 110.0), ("b", 90.0)]), 10.0);
+        assert!(!regressed, "exactly at the threshold is not a regression");
+        let (summary, regressed) = compare(&baseline, &record(&[("a", 111.0)]), 10.0);
+        assert!(regressed);
+        assert!(summary.contains("a: ") && summary.contains("REGRESSION"));
+        let (summary, regressed) = compare(&baseline, &record(&[("c", 500.0)]), 10.0);
+        assert!(!regressed);
+        assert!(summary.contains("c: no baseline"));
+    }
+
+    #[test]
+    fn invalid_baselines_fail() {
+        for base in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            let (summary, regressed) =
+                compare(&record(&[("a", base)]), &record(&[("a", 1.0)]), 10.0);
+            assert!(regressed, "baseline {base}");
+            assert!(summary.contains("INVALID BASELINE"));
+        }
+    }
+
+    #[test]
+    fn civil_dates() {
+        let at = |timestamp| BenchRecord {
+            timestamp,
+            ..record(&[])
+        };
+        assert_eq!(at(0).formatted_time(), "1970-01-01 00:00:00 UTC");
+        assert_eq!(at(951_782_400).formatted_time(), "2000-02-29 00:00:00 UTC");
+        assert_eq!(
+            at(1_700_000_000).formatted_time(),
+            "2023-11-14 22:13:20 UTC"
+        );
+    }
+}
@@ -0,0 +1,323 @@
+use std::{
+    fmt::Display,
+    fs::{self, File},
+    io::{self, PipeReader, PipeWriter, Read, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::unix::pipe,
+};
+
+use crate::impls::{
+    affinity::AffinityConfig,
+    bench::{BenchControl, BenchReport},
+    thread_names::spawn_named,
+};
+
+const FILE_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockingOp {
+    Sleep,
+    FileRead,
+    PipeRead,
+}
+
+impl BlockingOp {
+    pub const ALL: [BlockingOp; 3] = [
+        BlockingOp::Sleep,
+        BlockingOp::FileRead,
+        BlockingOp::PipeRead,
+    ];
+}
+
+impl Display for BlockingOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            BlockingOp::Sleep => "Sleep",
+            BlockingOp::FileRead => "File read",
+            BlockingOp::PipeRead => "Pipe read",
+        };
+        f.write_str(str)
+    }
+}
+
+/// Reads `path` the way a slow disk serves it: dropped from the page cache first, then
+/// answered after `latency`, blocking the calling thread throughout
+fn read_cold(path: &Path, latency: Duration) -> io::Result<Vec<u8>> {
+    let mut file = File::open(path)?;
+    evict_from_page_cache(&file);
+    thread::sleep(latency);
+    let mut contents = Vec::with_capacity(FILE_SIZE);
+    file.read_to_end(&mut contents)?;
+    Ok(contents)
+}
+
+/// Only clean pages can be dropped, which is why the files are synced once written
+#[cfg(target_os = "linux")]
+fn evict_from_page_cache(file: &File) {
+    use std::os::fd::AsRawFd;
+    // SAFETY: posix_fadvise takes an open descriptor and plain integers, and touches no memory
+    unsafe {
+        libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_DONTNEED);
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn evict_from_page_cache(_file: &File) {}
+
+/// Everything a single worker needs to perform any of the blocking operations.
+/// The pipe is answered by a device thread that replies to each request byte
+/// after the configured latency, so a read blocks until the "hardware" responds
+struct IoTarget {
+    file: PathBuf,
+    request: PipeWriter,
+    response: PipeReader,
+}
+
+impl IoTarget {
+    fn perform(&mut self, op: BlockingOp, latency: Duration) {
+        match op {
+            BlockingOp::Sleep => thread::sleep(latency),
+            BlockingOp::FileRead => {
+                let _ = read_cold(&self.file, latency);
+            }
+            BlockingOp::PipeRead => {
+                let _ = self.request.write_all(&[0]);
+                let _ = self.response.read_exact(&mut [0]);
+            }
+        }
+    }
+
+    /// Must be called from inside a tokio runtime, since the pipes get registered with its reactor
+    fn into_async(self) -> AsyncIoTarget {
+        AsyncIoTarget {
+            file: self.file,
+            request: pipe::Sender::from_owned_fd(self.request.into()).expect("request pipe"),
+            response: pipe::Receiver::from_owned_fd(self.response.into()).expect("response pipe"),
+        }
+    }
+}
+
+struct AsyncIoTarget {
+    file: PathBuf,
+    request: pipe::Sender,
+    response: pipe::Receiver,
+}
+
+impl AsyncIoTarget {
+    async fn perform(&mut self, op: BlockingOp, latency: Duration) {
+        match op {
+            BlockingOp::Sleep => tokio::time::sleep(latency).await,
+            BlockingOp::FileRead => {
+                //What tokio::fs does too, since a file read cannot be awaited
+                let file = self.file.clone();
+                let _ = tokio::task::spawn_blocking(move || read_cold(&file, latency)).await;
+            }
+            BlockingOp::PipeRead => {
+                let _ = self.request.write_all(&[0]).await;
+                let _ = self.response.read_exact(&mut [0]).await;
+            }
+        }
+    }
+}
+
+fn spawn_device(latency: Duration) -> (PipeWriter, PipeReader, JoinHandle<()>) {
+    let (mut request_rx, request_tx) = std::io::pipe().expect("failed to create pipe");
+    let (response_rx, mut response_tx) = std::io::pipe().expect("failed to create pipe");
+    let handle = spawn_named("bench-device".to_string(), move || {
+        let mut buf = [0];
+        while let Ok(1) = request_rx.read(&mut buf) {
+            thread::sleep(latency);
+            if response_tx.write_all(&buf).is_err() {
+                break;
+            }
+        }
+    });
+    (request_tx, response_rx, handle)
+}
+
+fn create_targets(
+    dir: &Path,
+    n_workers: usize,
+    latency: Duration,
+) -> (Vec<IoTarget>, Vec<JoinHandle<()>>) {
+    let mut targets = Vec::with_capacity(n_workers);
+    let mut devices = Vec::with_capacity(n_workers);
+    for i in 0..n_workers {
+        let file = dir.join(format!("worker_{i}.bin"));
+        File::create(&file)
+            .and_then(|mut f| {
+                f.write_all(&vec![i as u8; FILE_SIZE])?;
+                f.sync_all()
+            })
+            .expect("failed to write benchmark file");
+        let (request, response, device) = spawn_device(latency);
+        targets.push(IoTarget {
+            file,
+            request,
+            response,
+        });
+        devices.push(device);
+    }
+    (targets, devices)
+}
+
+fn bench_io_many_to_one(
+    mut targets: Vec<IoTarget>,
+    op: BlockingOp,
+    ops_per_worker: usize,
+    latency: Duration,
+    control: &Arc<BenchControl>,
+    //Runs on the calling thread, which has already been restricted to the configured CPUs
+    _affinity: &AffinityConfig,
+) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ops_per_worker {
+        for target in targets.iter_mut() {
+            if control.is_cancelled() {
+                return start.elapsed();
+            }
+            target.perform(op, latency);
+            control.advance();
+        }
+    }
+    start.elapsed()
+}
+
+fn bench_io_one_to_one(
+    targets: Vec<IoTarget>,
+    op: BlockingOp,
+    ops_per_worker: usize,
+    latency: Duration,
+    control: &Arc<BenchControl>,
+    affinity: &AffinityConfig,
+) -> Duration {
+    let start = Instant::now();
+    let handles: Vec<JoinHandle<()>> = targets
+        .into_iter()
+        .enumerate()
+        .map(|(i, mut target)| {
+            let control = control.clone();
+            let affinity = affinity.clone();
+            spawn_named(format!("bench-{i}"), move || {
+                affinity.pin_current_thread(i);
+                for _ in 0..ops_per_worker {
+                    if control.is_cancelled() {
+                        break;
+                    }
+                    target.perform(op, latency);
+                    control.advance();
+                }
+            })
+        })
+        .collect();
+    for h in handles {
+        let _ = h.join();
+    }
+    start.elapsed()
+}
+
+fn bench_io_many_to_many(
+    targets: Vec<IoTarget>,
+    op: BlockingOp,
+    ops_per_worker: usize,
+    latency: Duration,
+    control: &Arc<BenchControl>,
+    affinity: &AffinityConfig,
+) -> Duration {
+    let rt = affinity.multi_thread_runtime();
+    rt.block_on(async {
+        let targets: Vec<AsyncIoTarget> = targets.into_iter().map(IoTarget::into_async).collect();
+
+        let start = Instant::now();
+        let handles: Vec<tokio::task::JoinHandle<()>> = targets
+            .into_iter()
+            .map(|mut target| {
+                let control = control.clone();
+                tokio::spawn(async move {
+                    for _ in 0..ops_per_worker {
+                        if control.is_cancelled() {
+                            break;
+                        }
+                        target.perform(op, latency).await;
+                        control.advance();
+                    }
+                })
+            })
+            .collect();
+        for h in handles {
+            let _ = h.await;
+        }
+        start.elapsed()
+    })
+}
+
+type IoBench =
+    fn(Vec<IoTarget>, BlockingOp, usize, Duration, &Arc<BenchControl>, &AffinityConfig) -> Duration;
+
+const IO_MODELS: [(&str, IoBench); 3] = [
+    ("Many-to-One (sequential)", bench_io_many_to_one),
+    ("One-to-One (OS threads)", bench_io_one_to_one),
+    ("Many-to-Many (async tasks)", bench_io_many_to_many),
+];
+
+pub fn run_io_benchmarks(
+    n_workers: usize,
+    ops_per_worker: usize,
+    latency: Duration,
+    control: &Arc<BenchControl>,
+    affinity: &AffinityConfig,
+) -> Option<BenchReport> {
+    affinity.restrict_current_thread();
+    let dir = std::env::temp_dir().join(format!("os_project_demo_io_{}", std::process::id()));
+    fs::create_dir_all(&dir).expect("failed to create benchmark directory");
+
+    let total_ops = n_workers * ops_per_worker;
+    let mut results = Vec::new();
+    let mut out = format!(
+        "Blocking IO results (workers = {n_workers}, ops/worker = {ops_per_worker}, latency = {latency:?})\n"
+    );
+    'ops: for op in BlockingOp::ALL {
+        out.push_str(&format!("\n{op}:\n"));
+
+        for (model, bench) in IO_MODELS {
+            let name = format!("{op} / {model}");
+            control.start_model(&name, total_ops);
+            let (targets, devices) = create_targets(&dir, n_workers, latency);
+            let dur = bench(targets, op, ops_per_worker, latency, control, affinity);
+            //The targets have been dropped by now, which closes the request pipes and lets the devices exit
+            for device in devices {
+                let _ = device.join();
+            }
+            if control.is_cancelled() {
+                break 'ops;
+            }
+
+            let per_sec = total_ops as f64 / dur.as_secs_f64();
+            let line = format!("{dur:?} total, {per_sec:.1} ops/s");
+            out.push_str(&format!("  {model}: {line}\n"));
+            control.finish_model(format!("{name}: {line}"));
+            results.push((name, dur.as_secs_f64() / total_ops as f64));
+        }
+    }
+
+    let _ = fs::remove_dir_all(&dir);
+    if control.is_cancelled() {
+        return None;
+    }
+    Some(BenchReport {
+        benchmark: "blocking-io",
+        params: format!(
+            "workers={n_workers} ops={ops_per_worker} latency_ms={} affinity={affinity}",
+            latency.as_millis()
+        ),
+        results,
+        text: out,
+    })
+}
@@ -0,0 +1,132 @@
+use std::{
+    collections::VecDeque,
+    fmt::{Display, Write as _},
+    fs,
+    path::Path,
+    sync::{LazyLock, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Entries kept before the oldest are dropped
+pub const CAPACITY: usize = 2000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    Model,
+    Task,
+    Thread,
+    Benchmark,
+    File,
+    Syscall,
+}
+
+impl EventKind {
+    pub const ALL: [EventKind; 6] = [
+        EventKind::Model,
+        EventKind::Task,
+        EventKind::Thread,
+        EventKind::Benchmark,
+        EventKind::File,
+        EventKind::Syscall,
+    ];
+}
+
+impl Display for EventKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            EventKind::Model => "model",
+            EventKind::Task => "task",
+            EventKind::Thread => "thread",
+            EventKind::Benchmark => "benchmark",
+            EventKind::File => "file",
+            EventKind::Syscall => "syscall",
+        };
+        f.write_str(str)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    /// Milliseconds since the Unix epoch
+    pub millis: u128,
+    pub kind: EventKind,
+    pub message: String,
+}
+
+impl LogEntry {
+    /// The time of day as `HH:MM:SS.mmm`, in UTC
+    pub fn formatted_time(&self) -> String {
+        let secs = (self.millis / 1000) % 86400;
+        format!(
+            "{:02}:{:02}:{:02}.{:03}",
+            secs / 3600,
+            secs % 3600 / 60,
+            secs % 60,
+            self.millis % 1000
+        )
+    }
+}
+
+impl Display for LogEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} [{}] {}",
+            self.formatted_time(),
+            self.kind,
+            self.message
+        )
+    }
+}
+
+static LOG: LazyLock<Mutex<VecDeque<LogEntry>>> = LazyLock::new(Default::default);
+
+pub fn log(kind: EventKind, message: impl Into<String>) {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+    let mut log = LOG.lock().unwrap();
+    if log.len() == CAPACITY {
+        log.pop_front();
+    }
+    log.push_back(LogEntry {
+        millis,
+        kind,
+        message: message.into(),
+    });
+}
+
+pub fn clear() {
+    LOG.lock().unwrap().clear();
+}
+
+/// Entries of the given kinds whose message contains `filter`, oldest first
+pub fn entries(kinds: &[EventKind], filter: &str) -> Vec<LogEntry> {
+    let filter = filter.to_lowercase();
+    LOG.lock()
+        .unwrap()
+        .iter()
+        .filter(|entry| kinds.contains(&entry.kind))
+        .filter(|entry| filter.is_empty() || entry.message.to_lowercase().contains(&filter))
+        .cloned()
+        .collect()
+}
+
+/// Writes the given entries as plain text, one per line
+pub fn export(path: &Path, entries: &[LogEntry]) -> std::io::Result<()> {
+    let mut out = String::new();
+    for entry in entries {
+        let _ = writeln!(out, "{entry}");
+    }
+    fs::write(path, out)
+}
+
+/// Shared by the image viewers of all three models
+pub fn file_picker_closed(model: &str, picked: Option<&Path>) {
+    let message = match picked {
+        Some(path) => format!("{model}: file picker closed, picked {}", path.display()),
+        None => format!("{model}: file picker closed without a file"),
+    };
+    log(EventKind::File, message);
+}
@@ -0,0 +1,411 @@
+use std::{
+    env::current_dir, fmt::Display, io, panic::resume_unwind, path::PathBuf, time::Duration,
+};
+
+use egui::{
+    Button, Color32, ColorImage, Context, DragValue, Id, Image, TextureHandle, TextureOptions,
+    load::SizedTexture,
+};
+use tracing::{Instrument, info_span};
+
+use crate::impls::{
+    DEFAULT_IMAGE,
+    animation::AnimationApp,
+    decode_image,
+    event_log::{self, EventKind},
+    form::SubmitForm,
+    image_processing::ImageProcessingApp,
+    syscall::{self, SyscallKind},
+    terminal::TerminalApp,
+    text_editor::TextEditor,
+    thread_model::ThreadModelKind,
+    thread_names::spawn_named,
+    thumbnails::FolderBrowser,
+};
+
+/// An interactive app that any thread model can host. The model decides which
+/// thread `show` runs on, the app only describes its UI and what it needs to block on
+pub trait ForegroundApp: Send {
+    /// Shows the app's windows. Anything that blocks must go through `blocker`
+    fn show(&mut self, ctx: &Context, blocker: Blocker);
+}
+
+/// Tells the model a frame is done when dropped, even while a panicking app unwinds,
+/// so the model never waits for a frame from a host that died. Hosts create one
+/// with their way of sending the signal right before calling `show`
+pub struct FrameDone<F: FnMut()>(pub F);
+
+impl<F: FnMut()> std::ops::Drop for FrameDone<F> {
+    fn drop(&mut self) {
+        (self.0)();
+    }
+}
+
+/// Keeps a folder of thousands of images from spawning thousands of threads at once,
+/// whether they are our own or tokio's blocking pool's
+const MAX_THREADS_IN_FLIGHT: usize = 64;
+
+/// The apps the launcher can start
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AppKind {
+    Demo,
+    ImageProcessing,
+    Animation,
+    Terminal,
+}
+
+impl AppKind {
+    pub const ALL: [AppKind; 4] = [
+        AppKind::Demo,
+        AppKind::ImageProcessing,
+        AppKind::Animation,
+        AppKind::Terminal,
+    ];
+
+    pub fn create(self, id: usize) -> Box<dyn ForegroundApp> {
+        match self {
+            AppKind::Demo => Box::new(DemoApp::new(id)),
+            AppKind::ImageProcessing => Box::new(ImageProcessingApp::new(id)),
+            AppKind::Animation => Box::new(AnimationApp::new(id)),
+            AppKind::Terminal => Box::new(TerminalApp::new(id)),
+        }
+    }
+}
+
+impl Display for AppKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            AppKind::Demo => "Demo",
+            AppKind::ImageProcessing => "Image processing",
+            AppKind::Animation => "Animation",
+            AppKind::Terminal => "Terminal",
+        };
+        f.write_str(str)
+    }
+}
+
+/// How the hosting model runs blocking work for its foreground apps
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Blocker {
+    /// Right away on the calling thread, stalling everything else on it (Many-to-One)
+    Inline,
+    /// On a new OS thread of its own (One-to-One)
+    Thread,
+    /// As a task on tokio's blocking pool (Many-to-Many)
+    Task,
+}
+
+impl Blocker {
+    pub fn model(self) -> ThreadModelKind {
+        match self {
+            Blocker::Inline => ThreadModelKind::ManyToOne,
+            Blocker::Thread => ThreadModelKind::OneToOne,
+            Blocker::Task => ThreadModelKind::ManyToMany,
+        }
+    }
+
+    /// Starts `job`. `name` names the thread or tracing span it runs in
+    pub fn run<T, F>(self, name: &str, job: F) -> Pending<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let span = info_span!("blocking", job = name);
+        let job = move || span.in_scope(job);
+        match self {
+            Blocker::Inline => Pending::Done(job()),
+            Blocker::Thread => Pending::Thread(spawn_named(format!("fg-{name}"), job)),
+            Blocker::Task => Pending::Task(tokio::task::spawn_blocking(job)),
+        }
+    }
+
+    /// Starts IO that comes in a blocking and an async flavour. Many-to-Many awaits the async
+    /// one in a task, the other models run the blocking one like any other blocking work
+    pub fn run_io<T, F, Fut>(self, name: &str, blocking: F, non_blocking: Fut) -> Pending<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        Fut: Future<Output = T> + Send + 'static,
+        T: Send + 'static,
+    {
+        match self {
+            Blocker::Task => Pending::Task(tokio::spawn(
+                non_blocking.instrument(info_span!("io", job = name)),
+            )),
+            Blocker::Inline | Blocker::Thread => self.run(name, blocking),
+        }
+    }
+
+    /// How many independent jobs an app should keep running at once. Inline jobs go one
+    /// per frame, so the only thread still gets to draw in between
+    pub fn max_in_flight(self) -> usize {
+        match self {
+            Blocker::Inline => 1,
+            //The blocking pool would otherwise grow to 512 threads before queueing
+            Blocker::Thread | Blocker::Task => MAX_THREADS_IN_FLIGHT,
+        }
+    }
+
+    /// Runs CPU-bound `jobs` the way the model spreads work: one after another inline,
+    /// each on a thread of its own, or each as a task on the runtime's workers
+    pub fn run_split<T, F>(self, name: &str, jobs: Vec<F>) -> Pending<Vec<T>>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let name = name.to_string();
+        let jobs: Vec<_> = jobs
+            .into_iter()
+            .enumerate()
+            .map(|(part, job)| {
+                let span = info_span!("cpu", job = %name, part);
+                move || span.in_scope(job)
+            })
+            .collect();
+        match self {
+            Blocker::Inline => Pending::Done(jobs.into_iter().map(|job| job()).collect()),
+            Blocker::Thread => Pending::Thread(spawn_named(format!("fg-{name}"), move || {
+                let handles: Vec<_> = jobs
+                    .into_iter()
+                    .enumerate()
+                    .map(|(part, job)| spawn_named(format!("fg-{name}-{part}"), job))
+                    .collect();
+                handles
+                    .into_iter()
+                    .map(|handle| handle.join().unwrap_or_else(|e| resume_unwind(e)))
+                    .collect()
+            })),
+            //Plain tasks rather than the blocking pool, so the work competes for the workers
+            Blocker::Task => Pending::Task(tokio::spawn(async move {
+                let handles: Vec<_> = jobs
+                    .into_iter()
+                    .map(|job| tokio::spawn(async move { job() }))
+                    .collect();
+                let mut results = Vec::with_capacity(handles.len());
+                for handle in handles {
+                    match handle.await {
+                        Ok(result) => results.push(result),
+                        Err(e) => resume_unwind(e.into_panic()),
+                    }
+                }
+                results
+            })),
+        }
+    }
+}
+
+impl Display for Blocker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            Blocker::Inline => "inline on the UI thread",
+            Blocker::Thread => "on its own thread",
+            Blocker::Task => "on the blocking pool",
+        };
+        f.write_str(str)
+    }
+}
+
+/// Blocking work started through a `Blocker`
+pub enum Pending<T> {
+    Done(T),
+    Thread(std::thread::JoinHandle<T>),
+    Task(tokio::task::JoinHandle<T>),
+}
+
+impl<T> Pending<T> {
+    pub fn is_finished(&self) -> bool {
+        match self {
+            Pending::Done(_) => true,
+            Pending::Thread(handle) => handle.is_finished(),
+            Pending::Task(handle) => handle.is_finished(),
+        }
+    }
+
+    /// Waits for the result, `None` if the job panicked
+    pub fn join(self) -> Option<T> {
+        match self {
+            Pending::Done(result) => Some(result),
+            Pending::Thread(handle) => handle.join().ok(),
+            Pending::Task(handle) => pollster::block_on(handle).ok(),
+        }
+    }
+}
+
+/// The original demo: an image viewer with a blocking file picker, a text editor and a form
+pub struct DemoApp {
+    /// Keeps the windows of several instances apart, egui would otherwise merge same-titled windows
+    id: usize,
+    /// The loaded image, `None` shows the default one. Replacing it frees the old texture
+    image: Option<TextureHandle>,
+    picker: Option<Pending<Option<PathBuf>>>,
+    decoding: Option<(PathBuf, Pending<Result<ColorImage, String>>)>,
+    image_error: Option<String>,
+    folder: FolderBrowser,
+    editor: TextEditor,
+    form: SubmitForm,
+    syscall_kind: SyscallKind,
+    syscall_ms: u64,
+    syscall: Option<Pending<io::Result<Duration>>>,
+    syscall_status: Option<String>,
+}
+
+impl DemoApp {
+    pub fn new(id: usize) -> Self {
+        Self {
+            id,
+            image: None,
+            picker: None,
+            decoding: None,
+            image_error: None,
+            folder: FolderBrowser::default(),
+            editor: TextEditor::new(),
+            form: SubmitForm::new(),
+            syscall_kind: SyscallKind::Sleep,
+            syscall_ms: 2000,
+            syscall: None,
+            syscall_status: None,
+        }
+    }
+
+    /// Both picking and decoding an image go through the model's blocking path
+    fn show_image_viewer(&mut self, ctx: &Context, blocker: Blocker) {
+        let model = blocker.model();
+        if let Some(picker) = self.picker.take_if(|picker| picker.is_finished()) {
+            let result = picker.join().flatten();
+            event_log::file_picker_closed(&model.to_string(), result.as_deref());
+            if let Some(path) = result {
+                self.load(ctx, path, blocker);
+            }
+        }
+        if let Some(path) = self.folder.show(ctx, blocker, self.id)
+            && self.decoding.is_none()
+        {
+            self.load(ctx, path, blocker);
+        }
+        if let Some((path, pending)) = self.decoding.take_if(|(_, pending)| pending.is_finished()) {
+            match pending.join() {
+                Some(Ok(image)) => {
+                    let name = format!("fg-{} {}", self.id, path.display());
+                    self.image = Some(ctx.load_texture(name, image, TextureOptions::default()));
+                    self.image_error = None;
+                }
+                Some(Err(e)) => self.image_error = Some(e),
+                None => self.image_error = Some(format!("Decoding {} panicked", path.display())),
+            }
+            if let Some(e) = &self.image_error {
+                event_log::log(EventKind::File, format!("{model}: fg-{} {e}", self.id));
+            }
+        }
+
+        self.window("Image Viewer").show(ctx, |ui| {
+            match &self.image {
+                Some(texture) => {
+                    ui.add(Image::new(SizedTexture::from_handle(texture)).shrink_to_fit())
+                }
+                None => ui.image(DEFAULT_IMAGE),
+            };
+            if let Some((path, _)) = &self.decoding {
+                ui.label(format!("Decoding {}...", path.display()));
+                ctx.request_repaint();
+            }
+            if let Some(e) = &self.image_error {
+                ui.colored_label(Color32::RED, e);
+            }
+
+            let idle = self.picker.is_none() && self.decoding.is_none() && !self.folder.is_busy();
+            ui.horizontal(|ui| {
+                if ui.add_enabled(idle, Button::new("Load Image")).clicked() {
+                    event_log::log(
+                        EventKind::File,
+                        format!("{model}: file picker opened {blocker}"),
+                    );
+                    let dialogue = rfd::FileDialog::new().set_directory(current_dir().unwrap());
+                    self.picker = Some(blocker.run("file-dialog", move || dialogue.pick_file()));
+                }
+                if ui.add_enabled(idle, Button::new("Open Folder")).clicked() {
+                    self.folder.open(blocker);
+                }
+            });
+        });
+    }
+
+    fn load(&mut self, ctx: &Context, path: PathBuf, blocker: Blocker) {
+        let job_path = path.clone();
+        //Large photos are scaled down in the job, off whichever thread draws the UI
+        let max_side = ctx.input(|i| i.max_texture_side);
+        let pending = blocker.run("image-decode", move || decode_image(&job_path, max_side));
+        self.decoding = Some((path, pending));
+    }
+
+    /// Issues a blocking system call through the model's blocking path
+    fn show_syscall(&mut self, ctx: &Context, blocker: Blocker) {
+        let model = blocker.model();
+        if let Some(pending) = self.syscall.take_if(|pending| pending.is_finished()) {
+            let status = match pending.join() {
+                Some(Ok(elapsed)) => format!("{} returned after {elapsed:.1?}", self.syscall_kind),
+                Some(Err(e)) => format!("{} failed: {e}", self.syscall_kind),
+                None => format!("{} panicked", self.syscall_kind),
+            };
+            event_log::log(
+                EventKind::Syscall,
+                format!("{model}: fg-{} {status}", self.id),
+            );
+            self.syscall_status = Some(status);
+        }
+
+        self.window("Blocking Syscall").show(ctx, |ui| {
+            let idle = self.syscall.is_none();
+            ui.add_enabled_ui(idle, |ui| {
+                egui::ComboBox::from_id_salt(("syscall kind", self.id))
+                    .selected_text(self.syscall_kind.to_string())
+                    .show_ui(ui, |ui| {
+                        for kind in SyscallKind::ALL {
+                            ui.selectable_value(&mut self.syscall_kind, kind, kind.to_string());
+                        }
+                    });
+                ui.horizontal(|ui| {
+                    ui.label("Duration: ");
+                    ui.add(
+                        DragValue::new(&mut self.syscall_ms)
+                            .range(0..=60_000)
+                            .suffix(" ms"),
+                    );
+                });
+            });
+            if ui.add_enabled(idle, Button::new("Issue")).clicked() {
+                let (kind, duration) = (self.syscall_kind, Duration::from_millis(self.syscall_ms));
+                event_log::log(
+                    EventKind::Syscall,
+                    format!(
+                        "{model}: fg-{} issued {kind} for {duration:?} {blocker}",
+                        self.id
+                    ),
+                );
+                self.syscall = Some(blocker.run("syscall", move || syscall::issue(kind, duration)));
+            }
+            if !idle {
+                ui.label(format!("Waiting for {}...", self.syscall_kind));
+                ctx.request_repaint();
+            } else if let Some(status) = &self.syscall_status {
+                ui.label(status);
+            }
+        });
+    }
+
+    fn window(&self, title: &str) -> egui::Window<'static> {
+        egui::Window::new(format!("{title} (fg-{})", self.id)).id(Id::new((title, self.id)))
+    }
+}
+
+impl ForegroundApp for DemoApp {
+    fn show(&mut self, ctx: &Context, blocker: Blocker) {
+        self.show_image_viewer(ctx, blocker);
+        self.show_syscall(ctx, blocker);
+
+        self.window("Text Editor").show(ctx, |ui| {
+            self.editor.show(ui, ctx, blocker);
+        });
+        self.window("Form").show(ctx, |ui| {
+            self.form.show(ui, ctx, blocker);
+        });
+    }
+}
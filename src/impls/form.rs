@@ -0,0 +1,139 @@
+use std::time::{Duration, Instant};
+
+use egui::{Button, Color32, Context, DragValue, Slider, Ui};
+
+use crate::impls::{
+    app::{DEFAULT_AGE, DEFAULT_NAME},
+    event_log::{self, EventKind},
+    foreground::{Blocker, Pending},
+    mock_server,
+    trace::json_string,
+};
+
+struct Submission {
+    started: Instant,
+    pending: Pending<Result<String, String>>,
+}
+
+/// The demo form, submitted as JSON to the in-process mock server
+pub struct SubmitForm {
+    name: String,
+    age: u32,
+    submission: Option<Submission>,
+    /// The server's answer to the last submission and how long it took
+    outcome: Option<(Result<String, String>, Duration)>,
+}
+
+impl SubmitForm {
+    pub fn new() -> Self {
+        Self {
+            name: DEFAULT_NAME.to_string(),
+            age: DEFAULT_AGE,
+            submission: None,
+            outcome: None,
+        }
+    }
+
+    fn submit(&mut self, blocker: Blocker) {
+        let json = format!(
+            "{{\"name\":{},\"age\":{}}}",
+            json_string(&self.name),
+            self.age
+        );
+        let blocking_json = json.clone();
+        let pending = blocker.run_io(
+            "form-submit",
+            move || mock_server::submit_blocking(&blocking_json),
+            mock_server::submit_async(json),
+        );
+        self.submission = Some(Submission {
+            started: Instant::now(),
+            pending,
+        });
+    }
+
+    pub fn show(&mut self, ui: &mut Ui, ctx: &Context, blocker: Blocker) {
+        if let Some(submission) = self
+            .submission
+            .take_if(|submission| submission.pending.is_finished())
+        {
+            let took = submission.started.elapsed();
+            let result = submission
+                .pending
+                .join()
+                .unwrap_or_else(|| Err("Submission panicked".to_string()));
+            let message = match &result {
+                Ok(body) => format!("form submitted in {took:.1?}, server answered {body}"),
+                Err(e) => format!("form submission failed after {took:.1?}: {e}"),
+            };
+            event_log::log(EventKind::Task, format!("{}: {message}", blocker.model()));
+            self.outcome = Some((result, took));
+        }
+
+        let idle = self.submission.is_none();
+        ui.add_enabled_ui(idle, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Name: ");
+                ui.text_edit_singleline(&mut self.name);
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Age: ");
+                ui.add(DragValue::new(&mut self.age));
+            });
+        });
+        if ui.add_enabled(idle, Button::new("Submit")).clicked() {
+            self.submit(blocker);
+        }
+        match (&self.submission, &self.outcome) {
+            (Some(submission), _) => {
+                ui.label(format!(
+                    "Submitting... {:.1?}",
+                    submission.started.elapsed()
+                ));
+                ctx.request_repaint();
+            }
+            (None, Some((Ok(body), took))) => {
+                ui.colored_label(Color32::GREEN, format!("Accepted in {took:.1?}: {body}"));
+            }
+            (None, Some((Err(e), took))) => {
+                ui.colored_label(Color32::RED, format!("Failed after {took:.1?}: {e}"));
+            }
+            (None, None) => {}
+        }
+
+        ui.collapsing("Mock server (shared by all apps)", |ui| {
+            let mut latency_ms = mock_server::latency().as_millis() as u64;
+            ui.horizontal(|ui| {
+                ui.label("Latency: ");
+                if ui
+                    .add(
+                        DragValue::new(&mut latency_ms)
+                            .range(0..=10_000)
+                            .suffix(" ms"),
+                    )
+                    .changed()
+                {
+                    mock_server::set_latency(Duration::from_millis(latency_ms));
+                }
+            });
+            let mut failure_percent = mock_server::failure_percent();
+            if ui
+                .add(Slider::new(&mut failure_percent, 0..=100).text("% failures"))
+                .changed()
+            {
+                mock_server::set_failure_percent(failure_percent);
+            }
+            match mock_server::address() {
+                Ok(address) => ui.label(format!("Listening on http://{address}/submit")),
+                Err(e) => ui.colored_label(Color32::RED, e),
+            };
+        });
+    }
+}
+
+impl Default for SubmitForm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,279 @@
+use std::{
+    env::current_dir,
+    fmt::Display,
+    ops::Range,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use egui::{
+    Button, Color32, Context, Id, Image, TextureHandle, TextureOptions, load::SizedTexture,
+};
+use image::{DynamicImage, RgbaImage, imageops};
+
+use crate::impls::{
+    event_log::{self, EventKind},
+    fit_texture,
+    foreground::{Blocker, ForegroundApp, Pending},
+    read_image, to_color_image,
+};
+
+const BLUR_SIGMA: f32 = 6.0;
+const LAPLACIAN: [f32; 9] = [0.0, -1.0, 0.0, -1.0, 4.0, -1.0, 0.0, -1.0, 0.0];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    Grayscale,
+    Blur,
+    EdgeDetect,
+}
+
+impl Filter {
+    pub const ALL: [Filter; 3] = [Filter::Grayscale, Filter::Blur, Filter::EdgeDetect];
+
+    /// Rows above and below a band the filter reads from
+    fn margin(self) -> u32 {
+        match self {
+            Filter::Grayscale => 0,
+            Filter::Blur => (BLUR_SIGMA * 3.0).ceil() as u32,
+            Filter::EdgeDetect => 1,
+        }
+    }
+
+    fn apply(self, image: RgbaImage) -> RgbaImage {
+        match self {
+            Filter::Grayscale => DynamicImage::ImageRgba8(image).grayscale().to_rgba8(),
+            Filter::Blur => imageops::blur(&image, BLUR_SIGMA),
+            Filter::EdgeDetect => {
+                //Filter a single channel, a Laplacian would wipe out an opaque alpha channel
+                let gray = DynamicImage::ImageRgba8(image).to_luma8();
+                DynamicImage::ImageLuma8(gray)
+                    .filter3x3(&LAPLACIAN)
+                    .to_rgba8()
+            }
+        }
+    }
+
+    /// Filters `rows` of `image`, reading a margin around them so the bands stitch seamlessly
+    fn apply_band(self, image: &RgbaImage, rows: Range<u32>) -> RgbaImage {
+        let margin = self.margin();
+        let top = rows.start.saturating_sub(margin);
+        let bottom = (rows.end + margin).min(image.height());
+        let part = imageops::crop_imm(image, 0, top, image.width(), bottom - top).to_image();
+        let filtered = self.apply(part);
+        imageops::crop_imm(
+            &filtered,
+            0,
+            rows.start - top,
+            image.width(),
+            rows.end - rows.start,
+        )
+        .to_image()
+    }
+}
+
+impl Display for Filter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            Filter::Grayscale => "Grayscale",
+            Filter::Blur => "Blur",
+            Filter::EdgeDetect => "Edge detect",
+        };
+        f.write_str(str)
+    }
+}
+
+/// One filtered band and when it was finished
+struct Band {
+    rows: Range<u32>,
+    image: RgbaImage,
+    cpu_time: Duration,
+    finished: Instant,
+}
+
+struct Run {
+    filter: Filter,
+    started: Instant,
+    bands: Pending<Vec<Band>>,
+}
+
+/// Applies filters to an image, split into bands that run under the active model.
+/// Unlike the demo app's blocking work, this keeps its threads busy on the CPU
+pub struct ImageProcessingApp {
+    id: usize,
+    source: Arc<RgbaImage>,
+    texture: Option<TextureHandle>,
+    filter: Filter,
+    run: Option<Run>,
+    picker: Option<Pending<Option<PathBuf>>>,
+    loading: Option<Pending<Result<RgbaImage, String>>>,
+    status: Option<String>,
+    error: Option<String>,
+}
+
+impl ImageProcessingApp {
+    pub fn new(id: usize) -> Self {
+        let source = image::load_from_memory(include_bytes!("../../assets/default.gif"))
+            .expect("bundled image")
+            .to_rgba8();
+        Self {
+            id,
+            source: Arc::new(source),
+            texture: None,
+            filter: Filter::Blur,
+            run: None,
+            picker: None,
+            loading: None,
+            status: None,
+            error: None,
+        }
+    }
+
+    fn set_image(&mut self, ctx: &Context, image: &RgbaImage) {
+        let name = format!("fg-{} image processing", self.id);
+        self.texture =
+            Some(ctx.load_texture(name, to_color_image(image), TextureOptions::default()));
+    }
+
+    fn start(&mut self, blocker: Blocker) {
+        let parts = std::thread::available_parallelism().map_or(4, |n| n.get()) as u32;
+        let height = self.source.height();
+        let rows_per_part = height.div_ceil(parts).max(1);
+        let filter = self.filter;
+        let jobs: Vec<_> = (0..height)
+            .step_by(rows_per_part as usize)
+            .map(|start| {
+                let rows = start..(start + rows_per_part).min(height);
+                let source = self.source.clone();
+                move || {
+                    let begin = Instant::now();
+                    let image = filter.apply_band(&source, rows.clone());
+                    Band {
+                        rows,
+                        image,
+                        cpu_time: begin.elapsed(),
+                        finished: Instant::now(),
+                    }
+                }
+            })
+            .collect();
+        event_log::log(
+            EventKind::Task,
+            format!(
+                "{}: fg-{} applying {filter} in {} part(s)",
+                blocker.model(),
+                self.id,
+                jobs.len()
+            ),
+        );
+        let started = Instant::now();
+        self.run = Some(Run {
+            filter,
+            started,
+            bands: blocker.run_split("filter", jobs),
+        });
+    }
+
+    fn finish(&mut self, ctx: &Context, run: Run) {
+        let Some(bands) = run.bands.join() else {
+            self.error = Some(format!("{} panicked", run.filter));
+            return;
+        };
+        let mut output = RgbaImage::new(self.source.width(), self.source.height());
+        let mut cpu_time = Duration::ZERO;
+        let mut finished = run.started;
+        for band in &bands {
+            imageops::replace(&mut output, &band.image, 0, band.rows.start as i64);
+            cpu_time += band.cpu_time;
+            finished = finished.max(band.finished);
+        }
+        self.status = Some(format!(
+            "{}: {:.1?} wall time, {cpu_time:.1?} CPU time over {} part(s)",
+            run.filter,
+            finished - run.started,
+            bands.len()
+        ));
+        self.set_image(ctx, &output);
+    }
+}
+
+impl ForegroundApp for ImageProcessingApp {
+    fn show(&mut self, ctx: &Context, blocker: Blocker) {
+        if self.texture.is_none() {
+            let source = self.source.clone();
+            self.set_image(ctx, &source);
+        }
+        if let Some(run) = self.run.take_if(|run| run.bands.is_finished()) {
+            self.finish(ctx, run);
+        }
+        if let Some(picker) = self.picker.take_if(|picker| picker.is_finished()) {
+            let result = picker.join().flatten();
+            event_log::file_picker_closed(&blocker.model().to_string(), result.as_deref());
+            if let Some(path) = result {
+                //Filtered images keep the source's size, so fitting the source covers both
+                let max_side = ctx.input(|i| i.max_texture_side);
+                self.loading = Some(blocker.run("image-decode", move || {
+                    read_image(&path).map(|image| fit_texture(image, max_side))
+                }));
+            }
+        }
+        if let Some(loading) = self.loading.take_if(|loading| loading.is_finished()) {
+            match loading.join() {
+                Some(Ok(image)) => {
+                    self.set_image(ctx, &image);
+                    self.source = Arc::new(image);
+                    self.error = None;
+                    self.status = None;
+                }
+                Some(Err(e)) => self.error = Some(e),
+                None => self.error = Some("Decoding panicked".to_string()),
+            }
+        }
+
+        let title = format!("Image Processing (fg-{})", self.id);
+        egui::Window::new(title)
+            .id(Id::new(("Image Processing", self.id)))
+            .show(ctx, |ui| {
+                let idle = self.run.is_none() && self.picker.is_none() && self.loading.is_none();
+                ui.horizontal(|ui| {
+                    ui.add_enabled_ui(idle, |ui| {
+                        for filter in Filter::ALL {
+                            ui.radio_value(&mut self.filter, filter, filter.to_string());
+                        }
+                    });
+                });
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(idle, Button::new("Apply")).clicked() {
+                        self.start(blocker);
+                    }
+                    if ui.add_enabled(idle, Button::new("Reset")).clicked() {
+                        let source = self.source.clone();
+                        self.set_image(ctx, &source);
+                        self.status = None;
+                    }
+                    if ui.add_enabled(idle, Button::new("Load Image")).clicked() {
+                        let dialogue = rfd::FileDialog::new().set_directory(current_dir().unwrap());
+                        self.picker =
+                            Some(blocker.run("file-dialog", move || dialogue.pick_file()));
+                    }
+                });
+                if let Some(run) = &self.run {
+                    ui.label(format!(
+                        "Applying {} for {:.1?}...",
+                        run.filter,
+                        run.started.elapsed()
+                    ));
+                    ctx.request_repaint();
+                } else if let Some(status) = &self.status {
+                    ui.label(status);
+                }
+                if let Some(e) = &self.error {
+                    ui.colored_label(Color32::RED, e);
+                }
+                if let Some(texture) = &self.texture {
+                    ui.add(Image::new(SizedTexture::from_handle(texture)).shrink_to_fit());
+                }
+            });
+    }
+}
@@ -0,0 +1,114 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, LazyLock, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::{self, ThreadId},
+};
+
+use crate::impls::thread_names::{TOKIO_BLOCKING, current_name, name_tokio_worker};
+
+/// Where one Many-to-Many task has been polled so far
+#[derive(Debug, Clone, Default)]
+pub struct TaskPlacement {
+    pub task: Arc<str>,
+    /// Polls per worker, indexed like `Placement::workers`
+    pub polls: Vec<u64>,
+    /// How often consecutive polls ran on different workers
+    pub migrations: u64,
+    last_worker: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Worker {
+    pub name: String,
+    pub thread: ThreadId,
+}
+
+/// Task-to-worker placement of every tracked task
+#[derive(Debug, Clone, Default)]
+pub struct Placement {
+    pub workers: Vec<Worker>,
+    pub tasks: Vec<TaskPlacement>,
+    index: HashMap<Arc<str>, usize>,
+}
+
+impl Placement {
+    pub fn total_migrations(&self) -> u64 {
+        self.tasks.iter().map(|t| t.migrations).sum()
+    }
+
+    /// Polls that ran on each worker, summed over all tasks
+    pub fn worker_polls(&self) -> Vec<u64> {
+        let mut polls = vec![0; self.workers.len()];
+        for task in self.tasks.iter() {
+            for (total, n) in polls.iter_mut().zip(task.polls.iter()) {
+                *total += n;
+            }
+        }
+        polls
+    }
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static PLACEMENT: LazyLock<Mutex<Placement>> = LazyLock::new(Default::default);
+
+/// Like the timeline, tasks only record while the placement window is open
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn reset() {
+    *PLACEMENT.lock().unwrap() = Placement::default();
+}
+
+pub fn snapshot() -> Placement {
+    PLACEMENT.lock().unwrap().clone()
+}
+
+/// Records that `task` is being polled on the calling worker thread
+pub fn record_poll(task: &Arc<str>) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let current = thread::current();
+    let mut placement = PLACEMENT.lock().unwrap();
+    let placement = &mut *placement;
+
+    let worker = match placement
+        .workers
+        .iter()
+        .position(|w| w.thread == current.id())
+    {
+        Some(worker) => worker,
+        None => {
+            //Tokio workers are only renamed in the kernel, and only once they first park
+            if current.name() == Some(TOKIO_BLOCKING) {
+                name_tokio_worker();
+            }
+            placement.workers.push(Worker {
+                name: current_name(),
+                thread: current.id(),
+            });
+            placement.workers.len() - 1
+        }
+    };
+    let i = *placement.index.entry(task.clone()).or_insert_with(|| {
+        placement.tasks.push(TaskPlacement {
+            task: task.clone(),
+            ..Default::default()
+        });
+        placement.tasks.len() - 1
+    });
+
+    let entry = &mut placement.tasks[i];
+    if entry.polls.len() <= worker {
+        entry.polls.resize(worker + 1, 0);
+    }
+    entry.polls[worker] += 1;
+    if entry.last_worker.is_some_and(|last| last != worker) {
+        entry.migrations += 1;
+    }
+    entry.last_worker = Some(worker);
+}
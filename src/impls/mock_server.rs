@@ -0,0 +1,212 @@
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream},
+    sync::{
+        LazyLock,
+        atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+use rand::Rng;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use crate::impls::{
+    event_log::{self, EventKind},
+    thread_names::try_spawn_named,
+};
+
+/// Larger request bodies are refused before any of them is read
+const MAX_BODY_BYTES: usize = 64 * 1024;
+/// Connections past this are refused instead of getting a thread each
+const MAX_CONNECTIONS: usize = 64;
+
+static SERVER: LazyLock<io::Result<SocketAddr>> = LazyLock::new(start);
+static LATENCY_MS: AtomicU64 = AtomicU64::new(500);
+static FAILURE_PERCENT: AtomicU32 = AtomicU32::new(10);
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+static CONNECTIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// Holds one of the `MAX_CONNECTIONS` slots until the connection's thread is done with it
+struct Connection;
+
+impl Connection {
+    fn acquire() -> Option<Self> {
+        CONNECTIONS
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+                (n < MAX_CONNECTIONS).then_some(n + 1)
+            })
+            .ok()
+            .map(|_| Connection)
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        CONNECTIONS.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// How long the server takes to answer, shared by every client
+pub fn latency() -> Duration {
+    Duration::from_millis(LATENCY_MS.load(Ordering::Relaxed))
+}
+
+pub fn set_latency(latency: Duration) {
+    LATENCY_MS.store(latency.as_millis() as u64, Ordering::Relaxed);
+}
+
+/// Share of submissions answered with a server error, in percent
+pub fn failure_percent() -> u32 {
+    FAILURE_PERCENT.load(Ordering::Relaxed)
+}
+
+pub fn set_failure_percent(percent: u32) {
+    FAILURE_PERCENT.store(percent.min(100), Ordering::Relaxed);
+}
+
+/// The loopback address of the server, which starts on first use
+pub fn address() -> Result<SocketAddr, String> {
+    SERVER
+        .as_ref()
+        .copied()
+        .map_err(|e| format!("Mock server failed to start: {e}"))
+}
+
+/// A minimal HTTP/1.1 server answering `POST /submit`, one thread per connection
+/// so a slow answer to one client never delays another
+fn start() -> io::Result<SocketAddr> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+    let address = listener.local_addr()?;
+    try_spawn_named("mock-server".to_string(), move || {
+        for mut stream in listener.incoming().flatten() {
+            let Some(connection) = Connection::acquire() else {
+                let _ = respond(
+                    &mut stream,
+                    "503 Service Unavailable",
+                    "too many connections",
+                );
+                continue;
+            };
+            //A failed spawn drops the closure, which closes the stream and frees the slot
+            if let Err(e) = try_spawn_named("mock-conn".to_string(), move || {
+                let _connection = connection;
+                let _ = handle(stream);
+            }) {
+                event_log::log(
+                    EventKind::Thread,
+                    format!("Mock server dropped a connection, no thread for it: {e}"),
+                );
+            }
+        }
+    })?;
+    Ok(address)
+}
+
+fn handle(mut stream: TcpStream) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim_end().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            content_length = value.trim().parse().unwrap_or(0);
+        }
+    }
+    if content_length > MAX_BODY_BYTES {
+        return respond(&mut stream, "413 Payload Too Large", "body too large");
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+
+    thread::sleep(latency());
+    if !request_line.starts_with("POST /submit ") {
+        respond(&mut stream, "404 Not Found", "not found")
+    } else if rand::rng().random_range(0..100) < failure_percent() {
+        respond(
+            &mut stream,
+            "500 Internal Server Error",
+            "simulated failure",
+        )
+    } else {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let body = format!("{{\"id\":{id}}}");
+        write_response(&mut stream, "201 Created", &body)
+    }
+}
+
+/// Answers with `{"error": error}`
+fn respond(stream: &mut TcpStream, status: &str, error: &str) -> io::Result<()> {
+    write_response(stream, status, &format!("{{\"error\":\"{error}\"}}"))
+}
+
+fn write_response(stream: &mut TcpStream, status: &str, body: &str) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+fn request(address: SocketAddr, json: &str) -> String {
+    format!(
+        "POST /submit HTTP/1.1\r\nHost: {address}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{json}",
+        json.len()
+    )
+}
+
+/// The body of a successful response, or what went wrong
+fn parse_response(response: &str) -> Result<String, String> {
+    let (head, body) = response
+        .split_once("\r\n\r\n")
+        .ok_or("Malformed response from the server")?;
+    //"HTTP/1.1 201 Created" without the version
+    let status_line = head.lines().next().unwrap_or_default();
+    let status = status_line
+        .split_once(' ')
+        .map_or(status_line, |(_, status)| status);
+    if status.starts_with('2') {
+        Ok(body.to_string())
+    } else {
+        Err(format!("Server answered {status}: {body}"))
+    }
+}
+
+/// Posts `json` with a blocking socket, the way Many-to-One and One-to-One do IO
+pub fn submit_blocking(json: &str) -> Result<String, String> {
+    let address = address()?;
+    let mut stream = TcpStream::connect(address).map_err(|e| e.to_string())?;
+    stream
+        .write_all(request(address, json).as_bytes())
+        .map_err(|e| e.to_string())?;
+    let mut response = String::new();
+    stream
+        .read_to_string(&mut response)
+        .map_err(|e| e.to_string())?;
+    parse_response(&response)
+}
+
+/// Posts `json` with a tokio socket, which yields its worker while waiting for the answer
+pub async fn submit_async(json: String) -> Result<String, String> {
+    let address = address()?;
+    let mut stream = tokio::net::TcpStream::connect(address)
+        .await
+        .map_err(|e| e.to_string())?;
+    stream
+        .write_all(request(address, &json).as_bytes())
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut response = String::new();
+    stream
+        .read_to_string(&mut response)
+        .await
+        .map_err(|e| e.to_string())?;
+    parse_response(&response)
+}
@@ -1,19 +1,66 @@
 use std::path::Path;
 
-use egui::{Context, ImageSource, include_image};
+use egui::{ColorImage, ImageSource, include_image};
+use image::{DynamicImage, ImageError, RgbaImage, imageops::FilterType};
 
+pub mod affinity;
+pub mod animation;
 pub mod app;
 pub mod async_light_thread;
 pub mod async_os_thread;
 pub mod bench;
+pub mod bench_channels;
+pub mod bench_history;
+pub mod bench_io;
+pub mod event_log;
+pub mod foreground;
+pub mod form;
+pub mod image_processing;
+pub mod migration;
+pub mod mock_server;
+pub mod proc_stats;
+pub mod runtime_metrics;
 pub mod sync;
+pub mod syscall;
+pub mod terminal;
+pub mod text_editor;
 pub mod thread_model;
+pub mod thread_names;
+pub mod thumbnails;
+pub mod timeline;
+pub mod trace;
 
-pub fn load_image(path: &Path, ctx: &Context) -> ImageSource<'static> {
-    let uri = format!("bytes://{path}", path = path.to_string_lossy());
-    let file = std::fs::read(path).unwrap();
-    ctx.include_bytes(uri.clone(), file);
-    ImageSource::Uri(uri.into())
+/// Reads and decodes an image file. This can take a while for large images, so callers
+/// run it through their model's `Blocker` rather than on the render path
+pub fn decode_image(path: &Path, max_side: usize) -> Result<ColorImage, String> {
+    read_image(path).map(|image| to_color_image(&fit_texture(image, max_side)))
+}
+
+pub fn read_image(path: &Path) -> Result<RgbaImage, String> {
+    let name = path.display();
+    let bytes = std::fs::read(path).map_err(|e| format!("Could not read {name}: {e}"))?;
+    let image = image::load_from_memory(&bytes).map_err(|e| match e {
+        ImageError::Unsupported(_) => format!("{name} is not a supported image format"),
+        e => format!("Could not decode {name}: {e}"),
+    })?;
+    Ok(image.to_rgba8())
+}
+
+/// Shrinks `image` to at most `max_side` pixels on either side, keeping its aspect ratio.
+/// egui cannot upload a texture larger than the renderer's `max_texture_side`
+pub fn fit_texture(image: RgbaImage, max_side: usize) -> RgbaImage {
+    let max_side = max_side.min(u32::MAX as usize) as u32;
+    if image.width() <= max_side && image.height() <= max_side {
+        return image;
+    }
+    DynamicImage::ImageRgba8(image)
+        .resize(max_side, max_side, FilterType::Triangle)
+        .to_rgba8()
+}
+
+pub fn to_color_image(image: &RgbaImage) -> ColorImage {
+    let size = [image.width() as usize, image.height() as usize];
+    ColorImage::from_rgba_unmultiplied(size, image.as_raw())
 }
 
 pub const DEFAULT_IMAGE: ImageSource<'static> = include_image!("../../assets/default.gif");
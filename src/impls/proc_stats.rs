@@ -0,0 +1,197 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+/// How often the panel re-reads /proc. Reading every task's stat file each frame
+/// gets expensive once One-to-One has spawned thousands of threads
+pub const REFRESH_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone)]
+pub struct ThreadStat {
+    pub tid: i32,
+    pub name: String,
+    /// R running, S sleeping, D uninterruptible (usually disk) sleep, etc.
+    pub state: char,
+    /// The CPU the thread last ran on
+    pub processor: u32,
+    pub cpu_time: Duration,
+    /// Share of one CPU used since the previous refresh
+    pub cpu_percent: f64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ProcSnapshot {
+    pub threads: usize,
+    pub voluntary_switches: u64,
+    pub involuntary_switches: u64,
+    pub cpu_time: Duration,
+    /// Share of one CPU used since the previous refresh, so it can exceed 100
+    pub cpu_percent: f64,
+    pub thread_stats: Vec<ThreadStat>,
+}
+
+impl ProcSnapshot {
+    /// How many threads are in each state, e.g. `[('R', 2), ('S', 30)]`
+    pub fn state_counts(&self) -> Vec<(char, usize)> {
+        let mut counts: Vec<(char, usize)> = Vec::new();
+        for stat in self.thread_stats.iter() {
+            match counts.iter_mut().find(|(state, _)| *state == stat.state) {
+                Some((_, n)) => *n += 1,
+                None => counts.push((stat.state, 1)),
+            }
+        }
+        counts.sort();
+        counts
+    }
+}
+
+/// Periodically sampled kernel-side view of this process
+#[derive(Default)]
+pub struct ProcStats {
+    last_refresh: Option<Instant>,
+    last_process_ticks: u64,
+    last_thread_ticks: HashMap<i32, u64>,
+    snapshot: ProcSnapshot,
+    error: Option<String>,
+}
+
+impl ProcStats {
+    /// Re-reads /proc if the refresh interval has passed and returns the latest snapshot
+    pub fn poll(&mut self) -> Result<&ProcSnapshot, &str> {
+        if self
+            .last_refresh
+            .is_none_or(|last| last.elapsed() >= REFRESH_INTERVAL)
+        {
+            if let Err(e) = self.refresh() {
+                self.error = Some(format!("Failed to read /proc: {e}"));
+            } else {
+                self.error = None;
+            }
+        }
+        match &self.error {
+            Some(e) => Err(e),
+            None => Ok(&self.snapshot),
+        }
+    }
+
+    fn refresh(&mut self) -> io::Result<()> {
+        let now = Instant::now();
+        let elapsed_ticks = self
+            .last_refresh
+            .map(|last| (now - last).as_secs_f64() * clock_ticks_per_second() as f64);
+
+        let status = fs::read_to_string("/proc/self/status")?;
+        let field = |name: &str| {
+            status
+                .lines()
+                .find_map(|line| line.strip_prefix(name))
+                .and_then(|value| value.trim().parse::<u64>().ok())
+                .unwrap_or_default()
+        };
+
+        let process = read_stat(Path::new("/proc/self/stat"))?;
+        let percent = |ticks: u64, last: u64| match elapsed_ticks {
+            Some(elapsed) if elapsed > 0.0 => ticks.saturating_sub(last) as f64 / elapsed * 100.0,
+            _ => 0.0,
+        };
+
+        let mut thread_ticks = HashMap::new();
+        let mut thread_stats = Vec::new();
+        for entry in fs::read_dir("/proc/self/task")? {
+            let entry = entry?;
+            let Some(tid) = entry.file_name().to_str().and_then(|t| t.parse().ok()) else {
+                continue;
+            };
+            //Threads can exit between listing the directory and reading their stat
+            let Ok(stat) = read_stat(&entry.path().join("stat")) else {
+                continue;
+            };
+            let last = self
+                .last_thread_ticks
+                .get(&tid)
+                .copied()
+                .unwrap_or(stat.ticks);
+            thread_stats.push(ThreadStat {
+                tid,
+                name: stat.name,
+                state: stat.state,
+                processor: stat.processor,
+                cpu_time: ticks_to_duration(stat.ticks),
+                cpu_percent: percent(stat.ticks, last),
+            });
+            thread_ticks.insert(tid, stat.ticks);
+        }
+        thread_stats.sort_by(|a, b| {
+            b.cpu_percent
+                .total_cmp(&a.cpu_percent)
+                .then(a.tid.cmp(&b.tid))
+        });
+
+        self.snapshot = ProcSnapshot {
+            threads: field("Threads:") as usize,
+            voluntary_switches: field("voluntary_ctxt_switches:"),
+            involuntary_switches: field("nonvoluntary_ctxt_switches:"),
+            cpu_time: ticks_to_duration(process.ticks),
+            cpu_percent: percent(process.ticks, self.last_process_ticks),
+            thread_stats,
+        };
+        self.last_process_ticks = process.ticks;
+        self.last_thread_ticks = thread_ticks;
+        self.last_refresh = Some(now);
+        Ok(())
+    }
+}
+
+struct Stat {
+    name: String,
+    state: char,
+    processor: u32,
+    /// utime + stime in clock ticks
+    ticks: u64,
+}
+
+/// Parses the fields of a `stat` file we care about, see proc_pid_stat(5)
+fn read_stat(path: &Path) -> io::Result<Stat> {
+    let contents = fs::read_to_string(path)?;
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "malformed stat file");
+    //The name is in parentheses and may itself contain spaces or parentheses
+    let open = contents.find('(').ok_or_else(invalid)?;
+    let close = contents.rfind(')').ok_or_else(invalid)?;
+    let name = contents[open + 1..close].to_string();
+    //Index 0 here is field 3 (state) in the man page numbering
+    let fields: Vec<&str> = contents[close + 1..].split_whitespace().collect();
+    let number = |index: usize| -> io::Result<u64> {
+        fields
+            .get(index)
+            .and_then(|f| f.parse().ok())
+            .ok_or_else(invalid)
+    };
+    Ok(Stat {
+        name,
+        state: fields
+            .first()
+            .and_then(|s| s.chars().next())
+            .ok_or_else(invalid)?,
+        processor: number(36)? as u32,
+        ticks: number(11)? + number(12)?,
+    })
+}
+
+fn ticks_to_duration(ticks: u64) -> Duration {
+    Duration::from_secs_f64(ticks as f64 / clock_ticks_per_second() as f64)
+}
+
+#[cfg(target_os = "linux")]
+fn clock_ticks_per_second() -> u64 {
+    // SAFETY: sysconf has no preconditions
+    let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    if ticks > 0 { ticks as u64 } else { 100 }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn clock_ticks_per_second() -> u64 {
+    100
+}
@@ -0,0 +1,126 @@
+use std::time::{Duration, Instant};
+
+use tokio::runtime::Handle;
+
+use crate::impls::proc_stats::REFRESH_INTERVAL;
+
+#[derive(Debug, Clone, Default)]
+pub struct WorkerStats {
+    /// Share of the last refresh interval the worker spent running tasks
+    pub busy_ratio: f64,
+    pub polls: u64,
+    /// Polls per second over the last refresh interval
+    pub poll_rate: f64,
+    pub local_queue_depth: usize,
+    pub steals: u64,
+    pub parks: u64,
+    /// Running or looking for work rather than parked
+    pub active: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RuntimeSnapshot {
+    pub workers: Vec<WorkerStats>,
+    pub alive_tasks: usize,
+    pub spawned_tasks: u64,
+    /// Tasks waiting in the shared injection queue, e.g. woken from outside the runtime
+    pub global_queue_depth: usize,
+    pub blocking_threads: usize,
+    pub idle_blocking_threads: usize,
+    pub blocking_queue_depth: usize,
+}
+
+/// Periodically sampled metrics of a tokio runtime
+pub struct RuntimeStats {
+    handle: Handle,
+    last_refresh: Option<Instant>,
+    last_busy: Vec<Duration>,
+    last_polls: Vec<u64>,
+    last_park_unpark: Vec<u64>,
+    snapshot: RuntimeSnapshot,
+}
+
+impl RuntimeStats {
+    pub fn new(handle: Handle) -> Self {
+        Self {
+            handle,
+            last_refresh: None,
+            last_busy: Vec::new(),
+            last_polls: Vec::new(),
+            last_park_unpark: Vec::new(),
+            snapshot: RuntimeSnapshot::default(),
+        }
+    }
+
+    /// Re-samples the metrics if the refresh interval has passed and returns the latest snapshot
+    pub fn poll(&mut self) -> &RuntimeSnapshot {
+        if self
+            .last_refresh
+            .is_none_or(|last| last.elapsed() >= REFRESH_INTERVAL)
+        {
+            self.refresh();
+        }
+        &self.snapshot
+    }
+
+    fn refresh(&mut self) {
+        let now = Instant::now();
+        let elapsed = self.last_refresh.map(|last| now - last);
+        let metrics = self.handle.metrics();
+
+        let n = metrics.num_workers();
+        let mut busy = Vec::with_capacity(n);
+        let mut polls = Vec::with_capacity(n);
+        let mut park_unpark = Vec::with_capacity(n);
+        let mut workers = Vec::with_capacity(n);
+        for i in 0..n {
+            busy.push(metrics.worker_total_busy_duration(i));
+            polls.push(metrics.worker_poll_count(i));
+            park_unpark.push(metrics.worker_park_unpark_count(i));
+            //An even count means the worker is not parked
+            let active = park_unpark[i] % 2 == 0;
+            let (mut busy_ratio, poll_rate) = match elapsed {
+                Some(elapsed) if !elapsed.is_zero() => {
+                    let last_busy = self.last_busy.get(i).copied().unwrap_or_default();
+                    let last_polls = self.last_polls.get(i).copied().unwrap_or_default();
+                    (
+                        (busy[i].saturating_sub(last_busy).as_secs_f64() / elapsed.as_secs_f64())
+                            .min(1.0),
+                        polls[i].saturating_sub(last_polls) as f64 / elapsed.as_secs_f64(),
+                    )
+                }
+                _ => (0.0, 0.0),
+            };
+            //Workers only publish their busy time between batches of polls, so one stuck
+            //in a blocking poll reports nothing. If it stayed unparked all interval without
+            //publishing anything, it spent the whole interval inside that poll
+            if active && busy_ratio == 0.0 && self.last_park_unpark.get(i) == Some(&park_unpark[i])
+            {
+                busy_ratio = 1.0;
+            }
+            workers.push(WorkerStats {
+                busy_ratio,
+                polls: polls[i],
+                poll_rate,
+                local_queue_depth: metrics.worker_local_queue_depth(i),
+                steals: metrics.worker_steal_count(i),
+                parks: metrics.worker_park_count(i),
+                active,
+            });
+        }
+
+        self.snapshot = RuntimeSnapshot {
+            workers,
+            alive_tasks: metrics.num_alive_tasks(),
+            spawned_tasks: metrics.spawned_tasks_count(),
+            global_queue_depth: metrics.global_queue_depth(),
+            blocking_threads: metrics.num_blocking_threads(),
+            idle_blocking_threads: metrics.num_idle_blocking_threads(),
+            blocking_queue_depth: metrics.blocking_queue_depth(),
+        };
+        self.last_busy = busy;
+        self.last_polls = polls;
+        self.last_park_unpark = park_unpark;
+        self.last_refresh = Some(now);
+    }
+}
@@ -1,83 +1,74 @@
 use std::{
-    env::current_dir,
     sync::{
         Arc,
         atomic::{AtomicU64, Ordering},
     },
     thread::sleep,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
-use egui::{Button, Context, DragValue, ImageSource};
+use egui::Context;
 use rand::Rng;
+use tracing::{info, info_span};
 
 use crate::impls::{
-    DEFAULT_IMAGE,
-    app::{DEFAULT_AGE, DEFAULT_NAME, DEFAULT_TEXT},
-    load_image,
-    thread_model::{ThreadModel, ThreadModelKind},
+    event_log::{self, EventKind},
+    foreground::{Blocker, ForegroundApp},
+    thread_model::{TaskIds, ThreadModel, ThreadModelKind},
+    timeline::{self, SpanKind},
 };
 
 pub struct SyncBackgroundTask {
+    label: Arc<str>,
     counter: Arc<AtomicU64>,
+    /// When the task last finished sleeping, from then on it waits for its turn on the UI thread
+    ready_since: Option<Instant>,
 }
 
 impl SyncBackgroundTask {
     pub fn run(&mut self) {
+        let _span = info_span!("task", task = %self.label).entered();
+        let start = Instant::now();
+        if let Some(ready_since) = self.ready_since {
+            timeline::record_off_thread(&self.label, SpanKind::Ready, ready_since, start);
+        }
         self.counter.fetch_add(1, Ordering::Relaxed);
         let duration = {
             let mut rng = rand::rng();
 
             rng.random_range(0..1000)
         };
+        timeline::record(&self.label, SpanKind::Run, start);
 
-        sleep(Duration::from_millis(duration));
+        //The only kernel thread blocks, so no other task can run until this returns
+        let start = Instant::now();
+        info_span!("sleep", ms = duration).in_scope(|| sleep(Duration::from_millis(duration)));
+        timeline::record(&self.label, SpanKind::Block, start);
+        self.ready_since = Some(Instant::now());
     }
 }
 
+/// Hosts a foreground app on the UI thread, so anything it blocks on stalls every task
 pub struct SyncForegroundTask {
-    image: ImageSource<'static>,
-    text_buffer: String,
-    form_name: String,
-    form_number: u32,
+    id: usize,
+    label: Arc<str>,
+    app: Box<dyn ForegroundApp>,
 }
 
 impl SyncForegroundTask {
-    pub fn new(image: ImageSource<'static>) -> Self {
+    pub fn new(id: usize, app: Box<dyn ForegroundApp>) -> Self {
         Self {
-            image,
-            text_buffer: DEFAULT_TEXT.to_string(),
-            form_name: DEFAULT_NAME.to_string(),
-            form_number: DEFAULT_AGE,
+            id,
+            label: format!("fg-{id}").into(),
+            app,
         }
     }
 
     pub fn show(&mut self, ctx: &egui::Context) {
-        egui::Window::new("Image Viewer").show(ctx, |ui| {
-            ui.image(self.image.clone());
-            if ui.add(Button::new("Load Image")).clicked() {
-                let dialogue = rfd::FileDialog::new().set_directory(current_dir().unwrap());
-                let result = dialogue.pick_file();
-                if let Some(result) = result {
-                    self.image = load_image(&result, ctx);
-                }
-            }
-        });
-
-        egui::Window::new("Text Editor").show(ctx, |ui| {
-            ui.text_edit_multiline(&mut self.text_buffer);
-        });
-        egui::Window::new("Form").show(ctx, |ui| {
-            ui.horizontal(|ui| {
-                ui.label("Name: ");
-                ui.text_edit_singleline(&mut self.form_name);
-            });
-
-            ui.horizontal(|ui| {
-                ui.label("Age: ");
-                ui.add(DragValue::new(&mut self.form_number));
-            });
-        });
+        let _span = info_span!("task", task = %self.label).entered();
+        let start = Instant::now();
+        self.app.show(ctx, Blocker::Inline);
+        timeline::record(&self.label, SpanKind::Run, start);
     }
 }
 
@@ -85,6 +76,7 @@ impl SyncForegroundTask {
 pub struct ManyToOneModel {
     foreground_tasks: Vec<SyncForegroundTask>,
     background_tasks: Vec<SyncBackgroundTask>,
+    background_ids: TaskIds,
 }
 
 impl ThreadModel for ManyToOneModel {
@@ -92,13 +84,43 @@ impl ThreadModel for ManyToOneModel {
         ThreadModelKind::ManyToOne
     }
 
-    fn create_foreground_task(&mut self) {
-        let image = DEFAULT_IMAGE;
-        self.foreground_tasks.push(SyncForegroundTask::new(image));
+    fn create_foreground_task(&mut self, id: usize, app: Box<dyn ForegroundApp>) {
+        let task = SyncForegroundTask::new(id, app);
+        info!(task = %task.label, "spawn");
+        event_log::log(
+            EventKind::Task,
+            format!(
+                "Many to One: foreground task {} started on the UI thread",
+                task.label
+            ),
+        );
+        self.foreground_tasks.push(task);
     }
 
-    fn create_background_task(&mut self, counter: Arc<AtomicU64>) {
-        self.background_tasks.push(SyncBackgroundTask { counter });
+    fn close_foreground_task(&mut self, id: usize) {
+        let Some(i) = self.foreground_tasks.iter().position(|task| task.id == id) else {
+            return;
+        };
+        self.foreground_tasks.remove(i);
+        event_log::log(
+            EventKind::Task,
+            format!("Many to One: foreground task fg-{id} closed"),
+        );
+    }
+
+    fn foreground_tasks(&self) -> Vec<usize> {
+        self.foreground_tasks.iter().map(|task| task.id).collect()
+    }
+
+    fn create_background_task(&mut self, counter: Arc<AtomicU64>) -> std::io::Result<()> {
+        let label = self.background_ids.next_label("bg");
+        info!(task = %label, "spawn");
+        self.background_tasks.push(SyncBackgroundTask {
+            label,
+            counter,
+            ready_since: None,
+        });
+        Ok(())
     }
 
     fn create_evil_task(&mut self) {}
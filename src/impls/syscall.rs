@@ -0,0 +1,167 @@
+use std::{
+    fmt::Display,
+    fs::{self, File},
+    io::{self, Read, Write},
+    os::unix::fs::FileExt,
+    path::Path,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::impls::thread_names::{current_tid, spawn_named};
+
+const BLOCK_SIZE: usize = 64 * 1024;
+
+/// A blocking system call that keeps the calling thread in the kernel for a chosen duration,
+/// without needing a desktop portal like the file picker does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyscallKind {
+    Sleep,
+    /// Opens and reads a named pipe whose writer only shows up after the duration,
+    /// like a slow device file
+    FileRead,
+    /// Reads an anonymous pipe that a device thread writes to after the duration
+    PipeRead,
+    /// Rewrites and syncs one block until the time is up
+    Fsync,
+}
+
+impl SyscallKind {
+    pub const ALL: [SyscallKind; 4] = [
+        SyscallKind::Sleep,
+        SyscallKind::FileRead,
+        SyscallKind::PipeRead,
+        SyscallKind::Fsync,
+    ];
+}
+
+impl Display for SyscallKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            SyscallKind::Sleep => "Sleep",
+            SyscallKind::FileRead => "File read",
+            SyscallKind::PipeRead => "Pipe read",
+            SyscallKind::Fsync => "Fsync",
+        };
+        f.write_str(str)
+    }
+}
+
+/// Blocks the calling thread in `kind` for about `duration` and returns how long it actually took
+pub fn issue(kind: SyscallKind, duration: Duration) -> io::Result<Duration> {
+    let start = Instant::now();
+    match kind {
+        SyscallKind::Sleep => thread::sleep(duration),
+        SyscallKind::FileRead => {
+            //A regular file would be served from the page cache without blocking at all,
+            //so read a FIFO instead. open() sleeps in the kernel until the writer opens it
+            let path = scratch_file();
+            make_fifo(&path)?;
+            let writer_path = path.clone();
+            let device = spawn_named("syscall-device".to_string(), move || {
+                thread::sleep(duration);
+                if let Ok(mut fifo) = File::options().write(true).open(writer_path) {
+                    let _ = fifo.write_all(&vec![0u8; BLOCK_SIZE]);
+                }
+            });
+            let result = File::open(&path).and_then(|mut fifo| {
+                let mut buf = vec![0u8; BLOCK_SIZE];
+                fifo.read_exact(&mut buf)
+            });
+            let _ = device.join();
+            let _ = fs::remove_file(&path);
+            result?;
+        }
+        SyscallKind::PipeRead => {
+            //A device thread answers after the duration, until then read() sleeps in the kernel
+            let (mut rx, mut tx) = io::pipe()?;
+            let device = spawn_named("syscall-device".to_string(), move || {
+                thread::sleep(duration);
+                let _ = tx.write_all(&[0]);
+            });
+            rx.read_exact(&mut [0])?;
+            let _ = device.join();
+        }
+        SyscallKind::Fsync => {
+            let path = scratch_file();
+            let result = (|| {
+                let file = File::create(&path)?;
+                let block = vec![0u8; BLOCK_SIZE];
+                //The same block every time, so the file stays small however long this runs
+                loop {
+                    file.write_all_at(&block, 0)?;
+                    file.sync_all()?;
+                    if start.elapsed() >= duration {
+                        return Ok::<_, io::Error>(());
+                    }
+                }
+            })();
+            let _ = fs::remove_file(&path);
+            result?;
+        }
+    }
+    Ok(start.elapsed())
+}
+
+/// Unique per thread, so concurrent calls do not share a file
+fn scratch_file() -> std::path::PathBuf {
+    std::env::temp_dir().join(format!(
+        "os_project_demo_syscall_{}_{}.bin",
+        std::process::id(),
+        current_tid()
+    ))
+}
+
+#[cfg(target_os = "linux")]
+fn make_fifo(path: &Path) -> io::Result<()> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes())?;
+    // SAFETY: mkfifo reads a NUL terminated path
+    if unsafe { libc::mkfifo(path.as_ptr(), 0o600) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn make_fifo(_path: &Path) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocks_for_about_the_duration() {
+        let duration = Duration::from_millis(100);
+        for kind in SyscallKind::ALL {
+            let took = issue(kind, duration).unwrap_or_else(|e| panic!("{kind}: {e}"));
+            assert!(took >= duration, "{kind} returned after {took:?}");
+            assert!(took < duration * 20, "{kind} took {took:?}");
+            assert!(!scratch_file().exists(), "{kind} left its scratch file");
+        }
+    }
+
+    #[test]
+    fn fsync_does_not_grow_the_file() {
+        let path = scratch_file();
+        let _ = fs::remove_file(&path);
+        //Watch the file from another thread while the call runs
+        let watched = path.clone();
+        let watcher = thread::spawn(move || {
+            let mut largest = 0;
+            for _ in 0..50 {
+                thread::sleep(Duration::from_millis(5));
+                if let Ok(meta) = fs::metadata(&watched) {
+                    largest = largest.max(meta.len());
+                }
+            }
+            largest
+        });
+        issue(SyscallKind::Fsync, Duration::from_millis(300)).unwrap();
+        assert!(watcher.join().unwrap() <= BLOCK_SIZE as u64);
+    }
+}
@@ -0,0 +1,397 @@
+use std::{
+    borrow::Cow,
+    collections::VecDeque,
+    io::{self, PipeReader, Read},
+    os::unix::process::CommandExt,
+    process::{Child, Command, ExitStatus, Stdio},
+    sync::{Arc, Mutex},
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use egui::{Button, Context, Id, Key, Label, RichText, ScrollArea, TextEdit, TextStyle};
+use tokio::{io::AsyncReadExt, net::unix::pipe, sync::oneshot};
+
+use crate::impls::{
+    event_log::{self, EventKind},
+    foreground::{Blocker, ForegroundApp},
+    thread_names::spawn_named,
+};
+
+/// Output kept per run, older output is dropped a line at a time
+pub const MAX_OUTPUT: usize = 256 * 1024;
+const READ_SIZE: usize = 64 * 1024;
+/// Longer lines are broken up, so no single row gets too wide to lay out
+const MAX_LINE: usize = 4096;
+const DEFAULT_COMMAND: &str = "find / -name '*.rs'";
+
+/// What the command wrote so far, stdout and stderr interleaved like in a real terminal.
+/// Kept as lines so a frame only lays out the ones that are visible
+#[derive(Default)]
+struct Output {
+    lines: VecDeque<String>,
+    /// Bytes in `lines`, counting their newlines
+    len: usize,
+    /// The unfinished last line, decoded once it ends so split UTF-8 survives
+    partial: Vec<u8>,
+    /// How the command ended, once it did
+    exit: Option<String>,
+}
+
+impl Output {
+    fn push(&mut self, bytes: &[u8]) {
+        self.partial.extend_from_slice(bytes);
+        let mut start = 0;
+        while let Some(end) = self.partial[start..]
+            .iter()
+            .take(MAX_LINE)
+            .position(|b| *b == b'\n')
+            .map(|newline| start + newline + 1)
+            .or_else(|| {
+                (self.partial.len() - start >= MAX_LINE)
+                    .then(|| char_boundary(&self.partial, start + MAX_LINE))
+            })
+        {
+            let line = String::from_utf8_lossy(&self.partial[start..end]);
+            let line = line.trim_end_matches('\n').to_string();
+            self.len += line.len() + 1;
+            self.lines.push_back(line);
+            start = end;
+        }
+        self.partial.drain(..start);
+        while self.len > MAX_OUTPUT
+            && let Some(line) = self.lines.pop_front()
+        {
+            self.len -= line.len() + 1;
+        }
+    }
+
+    fn rows(&self) -> usize {
+        self.lines.len() + usize::from(!self.partial.is_empty()) + usize::from(self.exit.is_some())
+    }
+
+    fn row(&self, row: usize) -> Cow<'_, str> {
+        if let Some(line) = self.lines.get(row) {
+            return Cow::Borrowed(line);
+        }
+        if row == self.lines.len() && !self.partial.is_empty() {
+            return String::from_utf8_lossy(&self.partial);
+        }
+        Cow::Borrowed(self.exit.as_deref().unwrap_or_default())
+    }
+}
+
+/// Moves `cut` back to the first byte of the UTF-8 sequence it falls into, so breaking
+/// a long line there leaves every character whole
+fn char_boundary(bytes: &[u8], mut cut: usize) -> usize {
+    //A sequence has at most three continuation bytes
+    let floor = cut.saturating_sub(3);
+    while cut > floor && bytes.get(cut).is_some_and(|b| b & 0xC0 == 0x80) {
+        cut -= 1;
+    }
+    cut
+}
+
+type SharedOutput = Arc<Mutex<Output>>;
+
+fn describe_exit(status: io::Result<ExitStatus>, took: Duration) -> String {
+    match status {
+        Ok(status) => format!("[{status} after {took:.1?}]"),
+        Err(e) => format!("[could not wait for the command: {e}]"),
+    }
+}
+
+/// A running command, read the way the hosting model does IO
+enum Running {
+    /// Read on the UI thread, one blocking read per frame
+    Inline { child: Child, pipe: PipeReader },
+    /// Read by a thread of its own until the pipe closes
+    Thread {
+        child: Child,
+        reader: JoinHandle<()>,
+    },
+    /// Awaited by a task, which also owns the child so it can reap it
+    Task {
+        pid: Option<u32>,
+        kill: Option<oneshot::Sender<()>>,
+        task: tokio::task::JoinHandle<()>,
+    },
+}
+
+/// Runs a shell command as a child process and streams its output into the window
+pub struct TerminalApp {
+    id: usize,
+    command: String,
+    output: SharedOutput,
+    running: Option<(Running, Instant)>,
+    error: Option<String>,
+}
+
+impl TerminalApp {
+    pub fn new(id: usize) -> Self {
+        Self {
+            id,
+            command: DEFAULT_COMMAND.to_string(),
+            output: Default::default(),
+            running: None,
+            error: None,
+        }
+    }
+
+    fn start(&mut self, blocker: Blocker) -> io::Result<Running> {
+        self.output = Default::default();
+        //Both streams go into one pipe, so their output stays in the order it was written
+        let (pipe, writer) = io::pipe()?;
+        let stderr = writer.try_clone()?;
+        let running = match blocker {
+            Blocker::Inline | Blocker::Thread => {
+                //In a group of its own, so a kill reaches every process of a pipeline
+                let child = Command::new("sh")
+                    .arg("-c")
+                    .arg(&self.command)
+                    .stdin(Stdio::null())
+                    .stdout(writer)
+                    .stderr(stderr)
+                    .process_group(0)
+                    .spawn()?;
+                if blocker == Blocker::Inline {
+                    Running::Inline { child, pipe }
+                } else {
+                    let output = self.output.clone();
+                    let reader = spawn_named(format!("fg-{}-pipe", self.id), move || {
+                        read_to_end(pipe, &output)
+                    });
+                    Running::Thread { child, reader }
+                }
+            }
+            Blocker::Task => {
+                let child = tokio::process::Command::new("sh")
+                    .arg("-c")
+                    .arg(&self.command)
+                    .stdin(Stdio::null())
+                    .stdout(writer)
+                    .stderr(stderr)
+                    .process_group(0)
+                    .kill_on_drop(true)
+                    .spawn()?;
+                let pid = child.id();
+                let pipe = pipe::Receiver::from_owned_fd(pipe.into())?;
+                let (kill_tx, kill_rx) = oneshot::channel();
+                let task = tokio::spawn(run_task(child, pipe, kill_rx, self.output.clone()));
+                Running::Task {
+                    pid,
+                    kill: Some(kill_tx),
+                    task,
+                }
+            }
+        };
+        Ok(running)
+    }
+
+    /// Advances the running command, returns whether it is still running
+    fn poll(running: &mut Running, started: Instant, output: &SharedOutput) -> bool {
+        match running {
+            Running::Inline { child, pipe } => {
+                //Blocks the UI thread until the command writes something or exits
+                let mut buf = vec![0; READ_SIZE];
+                match pipe.read(&mut buf) {
+                    //A signal cut the read short, the command is still running
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => true,
+                    Ok(0) | Err(_) => {
+                        let exit = describe_exit(child.wait(), started.elapsed());
+                        output.lock().unwrap().exit = Some(exit);
+                        false
+                    }
+                    Ok(n) => {
+                        output.lock().unwrap().push(&buf[..n]);
+                        true
+                    }
+                }
+            }
+            Running::Thread { child, reader } => {
+                if !reader.is_finished() {
+                    return true;
+                }
+                match child.try_wait() {
+                    //The pipe closed but the command has not exited yet
+                    Ok(None) => true,
+                    Ok(Some(status)) => {
+                        let exit = describe_exit(Ok(status), started.elapsed());
+                        output.lock().unwrap().exit = Some(exit);
+                        false
+                    }
+                    Err(e) => {
+                        output.lock().unwrap().exit =
+                            Some(describe_exit(Err(e), started.elapsed()));
+                        false
+                    }
+                }
+            }
+            Running::Task { task, .. } => !task.is_finished(),
+        }
+    }
+
+    fn kill(running: &mut Running) {
+        match running {
+            Running::Inline { child, .. } | Running::Thread { child, .. } => {
+                kill_group(child.id());
+                let _ = child.kill();
+            }
+            Running::Task { pid, kill, task } => {
+                //A finished task has reaped the child, its group id may belong to someone else
+                if let Some(pid) = pid
+                    && !task.is_finished()
+                {
+                    kill_group(*pid);
+                }
+                if let Some(kill) = kill.take() {
+                    let _ = kill.send(());
+                }
+            }
+        }
+    }
+}
+
+/// Kills the process group led by `pid`. Killing only the shell would leave the rest
+/// of a pipeline running, holding the pipe open so the reader never sees its end
+#[cfg(target_os = "linux")]
+fn kill_group(pid: u32) {
+    //SAFETY: killpg only takes plain integers. The group is the child's own, made by
+    //process_group(0), and lives on until the child is reaped
+    unsafe {
+        libc::killpg(pid as libc::pid_t, libc::SIGKILL);
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn kill_group(_pid: u32) {}
+
+fn read_to_end(mut pipe: PipeReader, output: &SharedOutput) {
+    let mut buf = vec![0; READ_SIZE];
+    loop {
+        match pipe.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => output.lock().unwrap().push(&buf[..n]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(_) => break,
+        }
+    }
+}
+
+async fn run_task(
+    mut child: tokio::process::Child,
+    mut pipe: pipe::Receiver,
+    kill: oneshot::Receiver<()>,
+    output: SharedOutput,
+) {
+    let started = Instant::now();
+    let read = async {
+        let mut buf = vec![0; READ_SIZE];
+        while let Ok(n) = pipe.read(&mut buf).await
+            && n > 0
+        {
+            output.lock().unwrap().push(&buf[..n]);
+        }
+    };
+    tokio::select! {
+        _ = read => {}
+        Ok(()) = kill => {
+            let _ = child.start_kill();
+        }
+    }
+    let exit = describe_exit(child.wait().await, started.elapsed());
+    output.lock().unwrap().exit = Some(exit);
+}
+
+impl ForegroundApp for TerminalApp {
+    fn show(&mut self, ctx: &Context, blocker: Blocker) {
+        if let Some((running, started)) = &mut self.running
+            && !Self::poll(running, *started, &self.output)
+        {
+            let exit = self.output.lock().unwrap().exit.clone().unwrap_or_default();
+            event_log::log(
+                EventKind::Task,
+                format!(
+                    "{}: fg-{} `{}` ended {exit}",
+                    blocker.model(),
+                    self.id,
+                    self.command
+                ),
+            );
+            self.running = None;
+        }
+
+        let title = format!("Terminal (fg-{})", self.id);
+        egui::Window::new(title)
+            .id(Id::new(("Terminal", self.id)))
+            .default_width(600.0)
+            .show(ctx, |ui| {
+                let idle = self.running.is_none();
+                ui.horizontal(|ui| {
+                    ui.label("$");
+                    let input = ui.add_enabled(
+                        idle,
+                        TextEdit::singleline(&mut self.command)
+                            .font(TextStyle::Monospace)
+                            .desired_width(400.0),
+                    );
+                    let submitted = input.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
+                    if idle && (ui.button("Run").clicked() || submitted) {
+                        match self.start(blocker) {
+                            Ok(running) => {
+                                event_log::log(
+                                    EventKind::Task,
+                                    format!(
+                                        "{}: fg-{} started `{}` {blocker}",
+                                        blocker.model(),
+                                        self.id,
+                                        self.command
+                                    ),
+                                );
+                                self.running = Some((running, Instant::now()));
+                                self.error = None;
+                            }
+                            Err(e) => {
+                                self.error = Some(format!("Could not start the command: {e}"))
+                            }
+                        }
+                    }
+                    if let Some((running, _)) = &mut self.running
+                        && ui.add(Button::new("Kill")).clicked()
+                    {
+                        Self::kill(running);
+                    }
+                });
+                if let Some(e) = &self.error {
+                    ui.colored_label(egui::Color32::RED, e);
+                }
+
+                let output = self.output.lock().unwrap();
+                let row_height = ui.text_style_height(&TextStyle::Monospace);
+                ScrollArea::both()
+                    .max_height(400.0)
+                    .stick_to_bottom(true)
+                    .show_rows(ui, row_height, output.rows(), |ui, rows| {
+                        for row in rows {
+                            //Unwrapped, so every row has the height show_rows expects
+                            ui.add(Label::new(RichText::new(output.row(row)).monospace()).extend());
+                        }
+                    });
+            });
+        if self.running.is_some() {
+            ctx.request_repaint();
+        }
+    }
+}
+
+impl std::ops::Drop for TerminalApp {
+    fn drop(&mut self) {
+        if let Some((mut running, _)) = self.running.take() {
+            Self::kill(&mut running);
+            //Reap the killed command, a task waits for its own child
+            if let Running::Inline { mut child, .. } | Running::Thread { mut child, .. } = running {
+                let _ = child.wait();
+            }
+        }
+    }
+}
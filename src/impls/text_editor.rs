@@ -0,0 +1,260 @@
+use std::{
+    env::current_dir,
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+
+use egui::{Button, Color32, Context, DragValue, TextEdit, Ui};
+
+use crate::impls::{
+    app::DEFAULT_TEXT,
+    event_log::{self, EventKind},
+    foreground::{Blocker, Pending},
+};
+
+/// A finished open or save, `None` if the dialog was cancelled
+type FileResult<T> = Option<(PathBuf, io::Result<T>)>;
+
+enum FileOp {
+    Open(Pending<FileResult<String>>),
+    /// Also remembers which edit was saved, so later edits stay marked as unsaved
+    Save {
+        edit: u64,
+        autosave: bool,
+        pending: Pending<FileResult<Duration>>,
+    },
+}
+
+impl FileOp {
+    fn is_finished(&self) -> bool {
+        match self {
+            FileOp::Open(pending) => pending.is_finished(),
+            FileOp::Save { pending, .. } => pending.is_finished(),
+        }
+    }
+}
+
+/// Reads a file, after waiting `latency` like a slow disk would
+pub fn slow_read(path: &Path, latency: Duration) -> io::Result<String> {
+    thread::sleep(latency);
+    fs::read_to_string(path)
+}
+
+/// Writes and syncs a file, after waiting `latency` like a slow disk would
+pub fn slow_write(path: &Path, text: &str, latency: Duration) -> io::Result<Duration> {
+    let start = Instant::now();
+    thread::sleep(latency);
+    let mut file = File::create(path)?;
+    file.write_all(text.as_bytes())?;
+    file.sync_all()?;
+    Ok(start.elapsed())
+}
+
+/// A text editor whose file IO goes through the model's blocking path
+pub struct TextEditor {
+    text: String,
+    path: Option<PathBuf>,
+    /// Counts edits, so a save can tell whether the text changed while it ran
+    edits: u64,
+    saved_edit: u64,
+    last_save: Instant,
+    autosave: bool,
+    autosave_secs: u64,
+    slow_disk_ms: u64,
+    op: Option<FileOp>,
+    status: Option<String>,
+    error: Option<String>,
+}
+
+impl TextEditor {
+    pub fn new() -> Self {
+        Self {
+            text: DEFAULT_TEXT.to_string(),
+            path: None,
+            edits: 0,
+            saved_edit: 0,
+            last_save: Instant::now(),
+            autosave: false,
+            autosave_secs: 5,
+            slow_disk_ms: 0,
+            op: None,
+            status: None,
+            error: None,
+        }
+    }
+
+    fn is_modified(&self) -> bool {
+        self.edits != self.saved_edit
+    }
+
+    fn latency(&self) -> Duration {
+        Duration::from_millis(self.slow_disk_ms)
+    }
+
+    fn open(&mut self, blocker: Blocker) {
+        let dialogue = rfd::FileDialog::new().set_directory(current_dir().unwrap());
+        let latency = self.latency();
+        self.op = Some(FileOp::Open(blocker.run("file-open", move || {
+            let path = dialogue.pick_file()?;
+            let text = slow_read(&path, latency);
+            Some((path, text))
+        })));
+    }
+
+    /// Saves to the current file, asking for one first if there is none yet
+    fn save(&mut self, blocker: Blocker, autosave: bool) {
+        let path = self.path.clone();
+        let text = self.text.clone();
+        let latency = self.latency();
+        let pending = blocker.run("file-save", move || {
+            let path = match path {
+                Some(path) => path,
+                None => rfd::FileDialog::new()
+                    .set_directory(current_dir().unwrap())
+                    .save_file()?,
+            };
+            let took = slow_write(&path, &text, latency);
+            Some((path, took))
+        });
+        self.op = Some(FileOp::Save {
+            edit: self.edits,
+            autosave,
+            pending,
+        });
+    }
+
+    fn finish(&mut self, op: FileOp, blocker: Blocker) {
+        let model = blocker.model();
+        match op {
+            FileOp::Open(pending) => match pending.join().flatten() {
+                Some((path, Ok(text))) => {
+                    event_log::log(
+                        EventKind::File,
+                        format!("{model}: opened {} in the text editor", path.display()),
+                    );
+                    self.text = text;
+                    self.path = Some(path);
+                    self.edits = 0;
+                    self.saved_edit = 0;
+                    self.last_save = Instant::now();
+                    self.status = None;
+                    self.error = None;
+                }
+                Some((path, Err(e))) => {
+                    self.error = Some(format!("Could not open {}: {e}", path.display()))
+                }
+                None => {}
+            },
+            FileOp::Save {
+                edit,
+                autosave,
+                pending,
+            } => {
+                self.last_save = Instant::now();
+                match pending.join().flatten() {
+                    Some((path, Ok(took))) => {
+                        let kind = if autosave { "autosaved" } else { "saved" };
+                        let status = format!("{kind} {} in {took:.1?}", path.display());
+                        event_log::log(EventKind::File, format!("{model}: {status}"));
+                        self.status = Some(status);
+                        self.error = None;
+                        self.saved_edit = edit;
+                        self.path = Some(path);
+                    }
+                    Some((path, Err(e))) => {
+                        let error = format!("Could not save {}: {e}", path.display());
+                        event_log::log(EventKind::File, format!("{model}: {error}"));
+                        self.error = Some(error);
+                    }
+                    None => {}
+                }
+            }
+        }
+    }
+
+    pub fn show(&mut self, ui: &mut Ui, ctx: &Context, blocker: Blocker) {
+        if let Some(op) = self.op.take_if(|op| op.is_finished()) {
+            self.finish(op, blocker);
+        }
+        //Only autosave to a file that was picked before, never pop up a dialog unasked
+        if self.autosave
+            && self.op.is_none()
+            && self.path.is_some()
+            && self.is_modified()
+            && self.last_save.elapsed() >= Duration::from_secs(self.autosave_secs)
+        {
+            self.save(blocker, true);
+        }
+
+        let idle = self.op.is_none();
+        ui.horizontal(|ui| {
+            if ui.add_enabled(idle, Button::new("Open")).clicked() {
+                self.open(blocker);
+            }
+            if ui.add_enabled(idle, Button::new("Save")).clicked() {
+                self.save(blocker, false);
+            }
+            ui.checkbox(&mut self.autosave, "Autosave every");
+            ui.add(
+                DragValue::new(&mut self.autosave_secs)
+                    .range(1..=600)
+                    .suffix(" s"),
+            );
+        });
+        ui.horizontal(|ui| {
+            ui.label("Slow disk latency: ");
+            ui.add(
+                DragValue::new(&mut self.slow_disk_ms)
+                    .range(0..=10_000)
+                    .suffix(" ms"),
+            );
+        });
+
+        let name = match &self.path {
+            Some(path) => path.display().to_string(),
+            None => "Untitled".to_string(),
+        };
+        let modified = if self.is_modified() {
+            " (modified)"
+        } else {
+            ""
+        };
+        ui.label(format!("{name}{modified}"));
+        match &self.op {
+            Some(FileOp::Open(_)) => {
+                ui.label("Opening...");
+            }
+            Some(FileOp::Save { .. }) => {
+                ui.label("Saving...");
+            }
+            None => {
+                if let Some(status) = &self.status {
+                    ui.label(status);
+                }
+            }
+        }
+        if let Some(e) = &self.error {
+            ui.colored_label(Color32::RED, e);
+        }
+
+        let opening = matches!(self.op, Some(FileOp::Open(_)));
+        if ui
+            .add_enabled(!opening, TextEdit::multiline(&mut self.text))
+            .changed()
+        {
+            self.edits += 1;
+        }
+        if self.op.is_some() || (self.autosave && self.is_modified()) {
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+    }
+}
+
+impl Default for TextEditor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,4 +1,6 @@
 use egui::Context;
+
+use crate::impls::foreground::ForegroundApp;
 use std::fmt::Display;
 use std::sync::Arc;
 use std::sync::atomic::AtomicU64;
@@ -21,10 +23,32 @@ impl Display for ThreadModelKind {
     }
 }
 
+/// Hands out the ids in background task labels like `bg-3`. Ids are never reused,
+/// so the timeline and migration rows of tasks stay apart
+#[derive(Debug, Default)]
+pub struct TaskIds(usize);
+
+impl TaskIds {
+    pub fn next_id(&mut self) -> usize {
+        self.0 += 1;
+        self.0 - 1
+    }
+
+    /// `<prefix>-<id>` with an id no other task got
+    pub fn next_label(&mut self, prefix: &str) -> Arc<str> {
+        format!("{prefix}-{}", self.next_id()).into()
+    }
+}
+
 pub trait ThreadModel {
     fn get_kind(&self) -> ThreadModelKind;
-    fn create_foreground_task(&mut self);
-    fn create_background_task(&mut self, counter: Arc<AtomicU64>);
+    /// Hosts `app` as foreground task `fg-<id>`
+    fn create_foreground_task(&mut self, id: usize, app: Box<dyn ForegroundApp>);
+    fn close_foreground_task(&mut self, id: usize);
+    /// Ids of the foreground tasks currently hosted
+    fn foreground_tasks(&self) -> Vec<usize>;
+    /// Fails when the model could not get a kernel thread for the task
+    fn create_background_task(&mut self, counter: Arc<AtomicU64>) -> std::io::Result<()>;
     fn create_evil_task(&mut self);
     fn num_background_tasks(&self) -> usize;
     fn run_interactive(&mut self, ctx: &Context);
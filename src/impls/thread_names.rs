@@ -0,0 +1,143 @@
+use std::{
+    cell::Cell,
+    fmt::Display,
+    io,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread::{self, JoinHandle},
+};
+
+/// What a kernel thread is used for, recovered from the name it was given.
+/// Linux keeps only the first 15 bytes of a thread name, so names stay short
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ThreadRole {
+    Ui,
+    Foreground,
+    Background,
+    TokioWorker,
+    TokioBlocking,
+    Bench,
+    Other,
+}
+
+impl ThreadRole {
+    pub fn from_name(name: &str) -> Self {
+        if name == "ui" {
+            ThreadRole::Ui
+        } else if name.starts_with("fg-") {
+            ThreadRole::Foreground
+        } else if name.starts_with("bg-") {
+            ThreadRole::Background
+        } else if name.starts_with("tokio-worker-") || name.starts_with("tokio-local-") {
+            ThreadRole::TokioWorker
+        } else if name == TOKIO_BLOCKING {
+            ThreadRole::TokioBlocking
+        } else if name.starts_with("bench-") {
+            ThreadRole::Bench
+        } else {
+            ThreadRole::Other
+        }
+    }
+
+    /// What runs on threads with this role
+    pub fn description(self) -> &'static str {
+        match self {
+            ThreadRole::Ui => "egui event loop, and every task of the Many-to-One model",
+            ThreadRole::Foreground => "one foreground task of the One-to-One model",
+            ThreadRole::Background => "one background task of the One-to-One model",
+            ThreadRole::TokioWorker => "fixed pool of workers running all Many-to-Many tasks",
+            ThreadRole::TokioBlocking => "tokio's blocking pool, grown on demand by spawn_blocking",
+            ThreadRole::Bench => "benchmark runner and its workers",
+            ThreadRole::Other => "created by a library, e.g. the windowing system",
+        }
+    }
+}
+
+impl Display for ThreadRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            ThreadRole::Ui => "UI",
+            ThreadRole::Foreground => "Foreground",
+            ThreadRole::Background => "Background",
+            ThreadRole::TokioWorker => "Tokio worker",
+            ThreadRole::TokioBlocking => "Tokio blocking",
+            ThreadRole::Bench => "Benchmark",
+            ThreadRole::Other => "Other",
+        };
+        f.write_str(str)
+    }
+}
+
+/// `thread::spawn` with a name, so the thread shows up by role in /proc, `top -H` and debuggers
+pub fn spawn_named<F, T>(name: String, f: F) -> JoinHandle<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    try_spawn_named(name, f).expect("failed to spawn thread")
+}
+
+/// Like `spawn_named`, but returns the error when the kernel refuses to create
+/// another thread, e.g. after hitting the thread limit
+pub fn try_spawn_named<F, T>(name: String, f: F) -> io::Result<JoinHandle<T>>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    thread::Builder::new().name(name).spawn(f)
+}
+
+/// Renames the calling thread as the kernel sees it. Used for the main thread,
+/// which std gives no way to name
+#[cfg(target_os = "linux")]
+pub fn name_current_thread(name: &str) {
+    let Ok(name) = std::ffi::CString::new(name) else {
+        return;
+    };
+    // SAFETY: PR_SET_NAME reads a NUL terminated string and truncates it to 16 bytes
+    unsafe {
+        libc::prctl(libc::PR_SET_NAME, name.as_ptr());
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn name_current_thread(_name: &str) {}
+
+/// What tokio names every thread it spawns, blocking pool and workers alike
+pub const TOKIO_BLOCKING: &str = "tokio-blocking";
+
+static NEXT_TOKIO_WORKER: AtomicUsize = AtomicUsize::new(0);
+
+/// Renames the calling thread to `tokio-worker-<n>` the first time it is called on it.
+/// Meant for `on_thread_park` and task polls, which only worker threads ever run, so the
+/// threads left named `TOKIO_BLOCKING` are the blocking pool
+pub fn name_tokio_worker() {
+    thread_local! {
+        static NAMED: Cell<bool> = const { Cell::new(false) };
+    }
+    if !NAMED.replace(true) {
+        name_current_thread(&format!(
+            "tokio-worker-{}",
+            NEXT_TOKIO_WORKER.fetch_add(1, Ordering::Relaxed)
+        ));
+    }
+}
+
+/// The kernel's id for the calling thread, the TID shown in /proc and the thread inspector
+#[cfg(target_os = "linux")]
+pub fn current_tid() -> i32 {
+    // SAFETY: gettid has no preconditions and cannot fail
+    unsafe { libc::gettid() }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn current_tid() -> i32 {
+    0
+}
+
+/// The name of the calling thread as the kernel sees it, which for the main
+/// thread differs from what std reports
+pub fn current_name() -> String {
+    std::fs::read_to_string("/proc/thread-self/comm")
+        .map(|name| name.trim_end().to_string())
+        .unwrap_or_else(|_| thread::current().name().unwrap_or("unnamed").to_string())
+}
@@ -0,0 +1,228 @@
+use std::{
+    env::current_dir,
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use egui::{
+    Button, Color32, ColorImage, Context, Id, Label, RichText, Spinner, TextureHandle,
+    TextureOptions, Vec2, load::SizedTexture,
+};
+use image::DynamicImage;
+
+use crate::impls::{
+    event_log::{self, EventKind},
+    foreground::{Blocker, Pending},
+    read_image, to_color_image,
+};
+
+/// Longest side of a thumbnail in pixels
+pub const THUMBNAIL_SIZE: u32 = 128;
+const EXTENSIONS: [&str; 4] = ["png", "jpg", "jpeg", "gif"];
+
+/// The images in `dir` the image viewer can decode, sorted by name
+pub fn list_images(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut images = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_image = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| EXTENSIONS.contains(&ext.to_lowercase().as_str()));
+        if is_image && path.is_file() {
+            images.push(path);
+        }
+    }
+    images.sort();
+    Ok(images)
+}
+
+/// Reads and decodes a whole image only to scale it down, a small IO plus CPU job
+pub fn make_thumbnail(path: &Path) -> Result<ColorImage, String> {
+    let image = read_image(path)?;
+    let thumbnail = DynamicImage::ImageRgba8(image)
+        .thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+        .to_rgba8();
+    Ok(to_color_image(&thumbnail))
+}
+
+enum Thumbnail {
+    Queued,
+    Loading(Pending<Result<ColorImage, String>>),
+    Ready(TextureHandle),
+    Failed(String),
+}
+
+type Listing = Option<(PathBuf, io::Result<Vec<PathBuf>>)>;
+
+/// A folder of images whose thumbnails are generated as one job per image
+#[derive(Default)]
+pub struct FolderBrowser {
+    opening: Option<Pending<Listing>>,
+    folder: Option<PathBuf>,
+    entries: Vec<(PathBuf, Thumbnail)>,
+    error: Option<String>,
+    started: Option<Instant>,
+    took: Option<Duration>,
+}
+
+impl FolderBrowser {
+    pub fn is_busy(&self) -> bool {
+        self.opening.is_some()
+    }
+
+    /// Picks a folder and lists its images, both through the model's blocking path
+    pub fn open(&mut self, blocker: Blocker) {
+        event_log::log(
+            EventKind::File,
+            format!("{}: folder picker opened {blocker}", blocker.model()),
+        );
+        let dialogue = rfd::FileDialog::new().set_directory(current_dir().unwrap());
+        self.opening = Some(blocker.run("folder-dialog", move || {
+            let dir = dialogue.pick_folder()?;
+            let images = list_images(&dir);
+            Some((dir, images))
+        }));
+    }
+
+    /// Collects finished thumbnails and starts queued ones, as many as the model keeps in flight
+    fn poll(&mut self, ctx: &Context, blocker: Blocker) {
+        let model = blocker.model();
+        if let Some(opening) = self.opening.take_if(|opening| opening.is_finished()) {
+            match opening.join().flatten() {
+                Some((dir, Ok(images))) => {
+                    event_log::log(
+                        EventKind::File,
+                        format!(
+                            "{model}: opened {} with {} image(s)",
+                            dir.display(),
+                            images.len()
+                        ),
+                    );
+                    self.entries = images
+                        .into_iter()
+                        .map(|path| (path, Thumbnail::Queued))
+                        .collect();
+                    self.folder = Some(dir);
+                    self.error = None;
+                    self.started = Some(Instant::now());
+                    self.took = None;
+                }
+                Some((dir, Err(e))) => {
+                    self.error = Some(format!("Could not list {}: {e}", dir.display()));
+                }
+                None => {}
+            }
+        }
+
+        let mut in_flight = 0;
+        for (path, thumbnail) in self.entries.iter_mut() {
+            match thumbnail {
+                Thumbnail::Loading(pending) if pending.is_finished() => {}
+                Thumbnail::Loading(_) => {
+                    in_flight += 1;
+                    continue;
+                }
+                _ => continue,
+            }
+            if let Thumbnail::Loading(pending) = std::mem::replace(thumbnail, Thumbnail::Queued) {
+                *thumbnail = match pending.join() {
+                    Some(Ok(image)) => Thumbnail::Ready(ctx.load_texture(
+                        format!("thumbnail {}", path.display()),
+                        image,
+                        TextureOptions::default(),
+                    )),
+                    Some(Err(e)) => Thumbnail::Failed(e),
+                    None => Thumbnail::Failed("Thumbnail job panicked".to_string()),
+                };
+            }
+        }
+        for (path, thumbnail) in self.entries.iter_mut() {
+            if in_flight >= blocker.max_in_flight() {
+                break;
+            }
+            if matches!(thumbnail, Thumbnail::Queued) {
+                let path = path.clone();
+                *thumbnail =
+                    Thumbnail::Loading(blocker.run("thumbnail", move || make_thumbnail(&path)));
+                in_flight += 1;
+            }
+        }
+
+        if in_flight > 0 {
+            ctx.request_repaint();
+        } else if let Some(started) = self.started.take() {
+            let took = started.elapsed();
+            event_log::log(
+                EventKind::File,
+                format!(
+                    "{model}: {} thumbnail(s) generated in {took:.1?}",
+                    self.entries.len()
+                ),
+            );
+            self.took = Some(took);
+        }
+    }
+
+    /// Shows the thumbnail grid of the open folder, returns the image that was clicked
+    pub fn show(&mut self, ctx: &Context, blocker: Blocker, id: usize) -> Option<PathBuf> {
+        self.poll(ctx, blocker);
+        let folder = self.folder.as_ref()?;
+        let mut open = true;
+        let mut clicked = None;
+        egui::Window::new(format!("{} (fg-{id})", folder.display()))
+            .id(Id::new(("Folder", id)))
+            .open(&mut open)
+            .default_width(4.5 * THUMBNAIL_SIZE as f32)
+            .show(ctx, |ui| {
+                let done = self
+                    .entries
+                    .iter()
+                    .filter(|(_, thumbnail)| {
+                        matches!(thumbnail, Thumbnail::Ready(_) | Thumbnail::Failed(_))
+                    })
+                    .count();
+                let mut progress = format!("{done}/{} thumbnails", self.entries.len());
+                if let Some(took) = self.took {
+                    progress.push_str(&format!(" in {took:.1?}"));
+                }
+                ui.label(progress);
+                if let Some(e) = &self.error {
+                    ui.colored_label(Color32::RED, e);
+                }
+
+                let cell = Vec2::splat(THUMBNAIL_SIZE as f32);
+                egui::ScrollArea::vertical()
+                    .max_height(600.0)
+                    .show(ui, |ui| {
+                        ui.horizontal_wrapped(|ui| {
+                            for (path, thumbnail) in &self.entries {
+                                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                                match thumbnail {
+                                    Thumbnail::Queued | Thumbnail::Loading(_) => {
+                                        ui.add_sized(cell, Spinner::new());
+                                    }
+                                    Thumbnail::Ready(texture) => {
+                                        let button =
+                                            Button::image(SizedTexture::from_handle(texture));
+                                        if ui.add(button).on_hover_text(name).clicked() {
+                                            clicked = Some(path.clone());
+                                        }
+                                    }
+                                    Thumbnail::Failed(e) => {
+                                        let label =
+                                            Label::new(RichText::new(name).color(Color32::RED));
+                                        ui.add_sized(cell, label).on_hover_text(e);
+                                    }
+                                }
+                            }
+                        });
+                    });
+            });
+        if !open {
+            *self = Self::default();
+        }
+        clicked
+    }
+}
@@ -0,0 +1,409 @@
+use std::{
+    cell::Cell,
+    collections::{HashMap, VecDeque},
+    fmt::Display,
+    fs,
+    future::Future,
+    pin::Pin,
+    sync::{
+        Arc, LazyLock, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    task::{Context, Poll, Wake, Waker},
+    time::{Duration, Instant},
+};
+
+use egui::{Align2, Color32, FontId, Rect, Sense, Stroke, pos2, vec2};
+
+use crate::impls::{
+    proc_stats::REFRESH_INTERVAL,
+    thread_names::{current_name, current_tid},
+};
+
+/// How many spans are kept before the oldest are dropped
+pub const CAPACITY: usize = 16384;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpanKind {
+    /// Executing on a kernel thread
+    Run,
+    /// Waiting for a timer, IO or a message
+    Block,
+    /// Could run, but is waiting for a thread to run on
+    Ready,
+}
+
+impl SpanKind {
+    fn color(self) -> Color32 {
+        match self {
+            SpanKind::Run => Color32::from_rgb(80, 180, 80),
+            SpanKind::Block => Color32::from_rgb(200, 70, 70),
+            SpanKind::Ready => Color32::from_rgb(220, 180, 60),
+        }
+    }
+}
+
+impl Display for SpanKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            SpanKind::Run => "run",
+            SpanKind::Block => "block",
+            SpanKind::Ready => "ready",
+        };
+        f.write_str(str)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Span {
+    pub task: Arc<str>,
+    /// The kernel thread the span happened on. `None` for a task that is parked
+    /// off-thread, like a blocked tokio task or a Many-to-One task waiting its turn
+    pub tid: Option<i32>,
+    pub kind: SpanKind,
+    pub start: Instant,
+    pub end: Instant,
+}
+
+#[derive(Default)]
+struct Timeline {
+    spans: VecDeque<Span>,
+    thread_names: HashMap<i32, String>,
+}
+
+thread_local! {
+    /// Whether this thread has put its name in the timeline. Every new thread starts
+    /// out false, so a thread that reuses the TID of an exited one records its own name
+    static NAME_RECORDED: Cell<bool> = const { Cell::new(false) };
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static TIMELINE: LazyLock<Mutex<Timeline>> = LazyLock::new(Default::default);
+
+/// Recording is off unless the timeline is shown, since every task of every model pays for it
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+pub fn clear() {
+    TIMELINE.lock().unwrap().spans.clear();
+}
+
+fn push(task: &Arc<str>, kind: SpanKind, start: Instant, end: Instant, on_thread: bool) {
+    if !is_enabled() {
+        return;
+    }
+    let tid = on_thread.then(current_tid);
+    let name = (on_thread && !NAME_RECORDED.replace(true)).then(current_name);
+    let mut timeline = TIMELINE.lock().unwrap();
+    if let (Some(tid), Some(name)) = (tid, name) {
+        timeline.thread_names.insert(tid, name);
+    }
+    if timeline.spans.len() == CAPACITY {
+        timeline.spans.pop_front();
+    }
+    timeline.spans.push_back(Span {
+        task: task.clone(),
+        tid,
+        kind,
+        start,
+        end,
+    });
+}
+
+/// Records that `task` spent from `start` until now in `kind` on the calling thread
+pub fn record(task: &Arc<str>, kind: SpanKind, start: Instant) {
+    push(task, kind, start, Instant::now(), true);
+}
+
+/// Records a span during which `task` was not on any kernel thread
+pub fn record_off_thread(task: &Arc<str>, kind: SpanKind, start: Instant, end: Instant) {
+    push(task, kind, start, end, false);
+}
+
+/// Remembers when the task it wraps was woken, so the time between a wake up
+/// and the next poll can be told apart from the time spent blocked
+struct TracedWaker {
+    inner: Waker,
+    woken_at: Mutex<Option<Instant>>,
+}
+
+impl Wake for TracedWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.woken_at
+            .lock()
+            .unwrap()
+            .get_or_insert_with(Instant::now);
+        self.inner.wake_by_ref();
+    }
+}
+
+/// Wraps a future so every poll is recorded as a run span on the worker that polled it,
+/// and the time between polls as block and ready spans
+pub struct Traced<F> {
+    task: Arc<str>,
+    inner: Pin<Box<F>>,
+    pending: Option<(Instant, Arc<TracedWaker>)>,
+}
+
+impl<F: Future> Traced<F> {
+    pub fn new(task: Arc<str>, inner: F) -> Self {
+        Self {
+            task,
+            inner: Box::pin(inner),
+            pending: None,
+        }
+    }
+}
+
+impl<F: Future> Future for Traced<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let start = Instant::now();
+        if let Some((pending_since, waker)) = self.pending.take() {
+            let woken_at = waker.woken_at.lock().unwrap().unwrap_or(start);
+            record_off_thread(&self.task, SpanKind::Block, pending_since, woken_at);
+            record_off_thread(&self.task, SpanKind::Ready, woken_at, start);
+        }
+        if !is_enabled() {
+            return self.inner.as_mut().poll(cx);
+        }
+
+        let waker = Arc::new(TracedWaker {
+            inner: cx.waker().clone(),
+            woken_at: Mutex::new(None),
+        });
+        let result = self
+            .inner
+            .as_mut()
+            .poll(&mut Context::from_waker(&Waker::from(waker.clone())));
+        record(&self.task, SpanKind::Run, start);
+        if result.is_pending() {
+            self.pending = Some((Instant::now(), waker));
+        }
+        result
+    }
+}
+
+/// State of the timeline window
+pub struct TimelineView {
+    /// Seconds of history shown
+    pub window: f32,
+    /// One row per task instead of one per kernel thread
+    pub by_task: bool,
+    paused_at: Option<Instant>,
+    names_refreshed: Option<Instant>,
+}
+
+impl Default for TimelineView {
+    fn default() -> Self {
+        Self {
+            window: 5.0,
+            by_task: false,
+            paused_at: None,
+            names_refreshed: None,
+        }
+    }
+}
+
+const ROW_HEIGHT: f32 = 18.0;
+const LABEL_WIDTH: f32 = 140.0;
+
+impl TimelineView {
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut self.by_task, false, "By kernel thread");
+            ui.radio_value(&mut self.by_task, true, "By task");
+            ui.add(egui::Slider::new(&mut self.window, 0.5..=30.0).text("seconds"));
+            let mut paused = self.paused_at.is_some();
+            if ui.checkbox(&mut paused, "Pause").changed() {
+                self.paused_at = paused.then(Instant::now);
+            }
+            if ui.button("Clear").clicked() {
+                clear();
+            }
+        });
+        ui.horizontal(|ui| {
+            for kind in [SpanKind::Run, SpanKind::Block, SpanKind::Ready] {
+                ui.colored_label(kind.color(), format!("■ {kind}"));
+            }
+        });
+
+        self.refresh_thread_names();
+        let now = self.paused_at.unwrap_or_else(Instant::now);
+        let from = now
+            .checked_sub(Duration::from_secs_f32(self.window))
+            .unwrap_or(now);
+        let Visible { rows, thread_names } = self.collect(from, now);
+
+        egui::ScrollArea::vertical().show_rows(ui, ROW_HEIGHT, rows.len(), |ui, range| {
+            for Row { label, spans } in &rows[range] {
+                let (rect, response) =
+                    ui.allocate_exact_size(vec2(ui.available_width(), ROW_HEIGHT), Sense::hover());
+                let painter = ui.painter_at(rect);
+                painter.text(
+                    rect.left_center(),
+                    Align2::LEFT_CENTER,
+                    label,
+                    FontId::monospace(11.0),
+                    ui.visuals().text_color(),
+                );
+                let track =
+                    Rect::from_min_max(pos2(rect.left() + LABEL_WIDTH, rect.top()), rect.max);
+                painter.line_segment(
+                    [track.left_bottom(), track.right_bottom()],
+                    Stroke::new(1.0, ui.visuals().faint_bg_color),
+                );
+                let x = |t: Instant| {
+                    let secs = t.saturating_duration_since(from).as_secs_f32();
+                    track.left() + track.width() * (secs / self.window).min(1.0)
+                };
+
+                let mut hovered = None;
+                for span in spans {
+                    let bar = Rect::from_min_max(
+                        pos2(x(span.start), track.top() + 2.0),
+                        pos2(x(span.end).max(x(span.start) + 1.0), track.bottom() - 2.0),
+                    );
+                    painter.rect_filled(bar, 0.0, span.kind.color());
+                    //In the thread view name the task, in the task view name the thread,
+                    //so migrations between workers show up as changing labels
+                    let text = if self.by_task {
+                        span.tid
+                            .and_then(|tid| thread_names.get(&tid).cloned())
+                            .unwrap_or_default()
+                    } else {
+                        span.task.to_string()
+                    };
+                    if bar.width() > 8.0 * text.len() as f32 {
+                        painter.text(
+                            bar.left_center() + vec2(2.0, 0.0),
+                            Align2::LEFT_CENTER,
+                            text,
+                            FontId::monospace(10.0),
+                            Color32::BLACK,
+                        );
+                    }
+                    if response
+                        .hover_pos()
+                        .is_some_and(|pos| bar.x_range().contains(pos.x))
+                    {
+                        hovered = Some(span);
+                    }
+                }
+                if let Some(span) = hovered {
+                    let on = span
+                        .tid
+                        .map(|tid| format!(" on TID {tid}"))
+                        .unwrap_or_default();
+                    response.on_hover_text_at_pointer(format!(
+                        "{}: {}{on} for {:.2?}",
+                        span.task,
+                        span.kind,
+                        span.end - span.start
+                    ));
+                }
+            }
+        });
+    }
+
+    /// The row a span belongs to, a TID or a task name depending on the view
+    fn row_key(span: &Span, by_task: bool) -> RowKey {
+        if by_task {
+            RowKey::Task(span.task.clone())
+        } else {
+            RowKey::Thread(span.tid)
+        }
+    }
+
+    /// Re-reads the names of the recorded threads that are still alive, since a thread
+    /// can be renamed after it first showed up. Throttled like the /proc panel
+    fn refresh_thread_names(&mut self) {
+        if self
+            .names_refreshed
+            .is_some_and(|at| at.elapsed() < REFRESH_INTERVAL)
+        {
+            return;
+        }
+        self.names_refreshed = Some(Instant::now());
+        //Read /proc without holding the lock every recording thread needs
+        let tids: Vec<i32> = TIMELINE
+            .lock()
+            .unwrap()
+            .thread_names
+            .keys()
+            .copied()
+            .collect();
+        let names: Vec<(i32, String)> = tids
+            .into_iter()
+            .filter_map(|tid| {
+                let name = fs::read_to_string(format!("/proc/self/task/{tid}/comm")).ok()?;
+                Some((tid, name.trim_end().to_string()))
+            })
+            .collect();
+        TIMELINE.lock().unwrap().thread_names.extend(names);
+    }
+
+    /// Every span between `from` and `to` bucketed into labelled rows, and the thread names
+    fn collect(&self, from: Instant, to: Instant) -> Visible {
+        let timeline = TIMELINE.lock().unwrap();
+        let mut index: HashMap<RowKey, usize> = HashMap::new();
+        let mut rows: Vec<Row> = Vec::new();
+        for span in timeline
+            .spans
+            .iter()
+            .filter(|span| span.end >= from && span.start <= to)
+            .filter(|span| self.by_task || span.tid.is_some())
+        {
+            let key = Self::row_key(span, self.by_task);
+            let row = *index.entry(key).or_insert_with_key(|key| {
+                let label = match key {
+                    RowKey::Task(task) => task.to_string(),
+                    RowKey::Thread(Some(tid)) => {
+                        let name = timeline.thread_names.get(tid).map_or("", String::as_str);
+                        format!("{name} ({tid})")
+                    }
+                    RowKey::Thread(None) => unreachable!("off-thread spans are filtered out"),
+                };
+                rows.push(Row {
+                    label,
+                    spans: Vec::new(),
+                });
+                rows.len() - 1
+            });
+            rows[row].spans.push(span.clone());
+        }
+        rows.sort_by(|a, b| a.label.cmp(&b.label));
+        Visible {
+            rows,
+            thread_names: timeline.thread_names.clone(),
+        }
+    }
+}
+
+/// What one frame of the timeline draws
+struct Visible {
+    /// In display order
+    rows: Vec<Row>,
+    thread_names: HashMap<i32, String>,
+}
+
+struct Row {
+    label: String,
+    spans: Vec<Span>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum RowKey {
+    Thread(Option<i32>),
+    Task(Arc<str>),
+}
@@ -0,0 +1,303 @@
+use std::{
+    collections::HashSet,
+    fmt::{self, Write as _},
+    fs,
+    path::Path,
+    sync::{
+        LazyLock, Mutex,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    time::Instant,
+};
+
+use tracing::{
+    Event, Metadata, Subscriber,
+    field::{Field, Visit},
+    span,
+    subscriber::Interest,
+};
+
+use crate::impls::thread_names::{current_name, current_tid};
+
+/// Events kept per recording, so a forgotten recording cannot eat all memory
+pub const MAX_EVENTS: usize = 1_000_000;
+
+/// One entry of the Chrome Trace Event format
+struct TraceEvent {
+    name: String,
+    /// `B` begin slice, `E` end slice, `i` instant, `M` metadata
+    phase: char,
+    /// Microseconds since the recording started
+    ts: f64,
+    tid: i32,
+    /// A JSON object
+    args: String,
+}
+
+/// Lives in its own allocation whose address is the span's id, so entering, exiting,
+/// cloning and closing a span need no global lock while nothing is being recorded
+struct SpanData {
+    name: String,
+    args: String,
+    refs: AtomicUsize,
+}
+
+impl SpanData {
+    fn from_id(id: &span::Id) -> &SpanData {
+        // SAFETY: every id this subscriber hands out is the address of a leaked Box<SpanData>
+        // that lives until try_close drops its last reference, and tracing only passes ids
+        // of spans that are still open
+        unsafe { &*(id.into_u64() as *const SpanData) }
+    }
+}
+
+#[derive(Default)]
+struct TraceState {
+    started: Option<Instant>,
+    events: Vec<TraceEvent>,
+    dropped: usize,
+    threads: HashSet<i32>,
+}
+
+impl TraceState {
+    fn push(&mut self, name: String, phase: char, args: String) {
+        let Some(started) = self.started.filter(|_| is_recording()) else {
+            return;
+        };
+        if self.events.len() >= MAX_EVENTS {
+            self.dropped += 1;
+            return;
+        }
+        let tid = current_tid();
+        if self.threads.insert(tid) {
+            self.events.push(TraceEvent {
+                name: "thread_name".to_string(),
+                phase: 'M',
+                ts: 0.0,
+                tid,
+                args: format!("{{\"name\":{}}}", json_string(&current_name())),
+            });
+        }
+        self.events.push(TraceEvent {
+            name,
+            phase,
+            ts: started.elapsed().as_secs_f64() * 1e6,
+            tid,
+            args,
+        });
+    }
+}
+
+static RECORDING: AtomicBool = AtomicBool::new(false);
+static STATE: LazyLock<Mutex<TraceState>> = LazyLock::new(Default::default);
+
+pub fn is_recording() -> bool {
+    RECORDING.load(Ordering::Relaxed)
+}
+
+/// Discards the previous recording and starts a new one
+pub fn start_recording() {
+    let mut state = STATE.lock().unwrap();
+    state.events.clear();
+    state.threads.clear();
+    state.dropped = 0;
+    state.started = Some(Instant::now());
+    RECORDING.store(true, Ordering::Relaxed);
+}
+
+pub fn stop_recording() {
+    RECORDING.store(false, Ordering::Relaxed);
+}
+
+/// Recorded events so far, and how many were dropped for exceeding `MAX_EVENTS`
+pub fn event_count() -> (usize, usize) {
+    let state = STATE.lock().unwrap();
+    (state.events.len(), state.dropped)
+}
+
+/// Writes the recording as Chrome Trace Event JSON, which Perfetto and chrome://tracing open
+pub fn export(path: &Path) -> std::io::Result<()> {
+    let state = STATE.lock().unwrap();
+    let pid = std::process::id();
+    let mut out = String::from("{\"displayTimeUnit\":\"ms\",\"traceEvents\":[\n");
+    for (i, event) in state.events.iter().enumerate() {
+        if i > 0 {
+            out.push_str(",\n");
+        }
+        let _ = write!(
+            out,
+            "{{\"name\":{},\"ph\":\"{}\",\"ts\":{:.3},\"pid\":{pid},\"tid\":{},\"args\":{}",
+            json_string(&event.name),
+            event.phase,
+            event.ts,
+            event.tid,
+            event.args
+        );
+        if event.phase == 'i' {
+            //Instant events are scoped to their thread rather than the whole process
+            out.push_str(",\"s\":\"t\"");
+        }
+        out.push('}');
+    }
+    out.push_str("\n]}\n");
+    fs::write(path, out)
+}
+
+/// `value` as a quoted and escaped JSON string
+pub fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Collects the fields of a span or event as JSON object members
+#[derive(Default)]
+struct Fields {
+    members: Vec<String>,
+    message: Option<String>,
+    task: Option<String>,
+}
+
+impl Fields {
+    fn to_json(&self) -> String {
+        format!("{{{}}}", self.members.join(","))
+    }
+
+    fn push(&mut self, field: &Field, json: String) {
+        self.members
+            .push(format!("{}:{json}", json_string(field.name())));
+    }
+}
+
+impl Visit for Fields {
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.push(field, value.to_string());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.push(field, value.to_string());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.push(field, value.to_string());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.record_debug(field, &format_args!("{value}"));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        let value = format!("{value:?}");
+        match field.name() {
+            "message" => self.message = Some(value),
+            "task" => {
+                self.push(field, json_string(&value));
+                self.task = Some(value);
+            }
+            _ => self.push(field, json_string(&value)),
+        }
+    }
+}
+
+/// A `tracing` subscriber that records this crate's spans and events while a recording
+/// is running. Entering and exiting a span become a slice on the current thread, so an
+/// instrumented future shows up as one slice per poll on whichever worker polled it
+pub struct ChromeTrace;
+
+impl ChromeTrace {
+    fn is_ours(metadata: &Metadata<'_>) -> bool {
+        metadata.target().starts_with(env!("CARGO_CRATE_NAME"))
+    }
+}
+
+impl Subscriber for ChromeTrace {
+    fn register_callsite(&self, metadata: &'static Metadata<'static>) -> Interest {
+        if !Self::is_ours(metadata) {
+            Interest::never()
+        } else if metadata.is_span() {
+            Interest::always()
+        } else {
+            //Events are only enabled while a recording runs
+            Interest::sometimes()
+        }
+    }
+
+    /// Spans are always tracked, since a task instrumented before the recording
+    /// started should still show its polls once it does
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        Self::is_ours(metadata) && (metadata.is_span() || is_recording())
+    }
+
+    fn new_span(&self, attrs: &span::Attributes<'_>) -> span::Id {
+        let mut fields = Fields::default();
+        attrs.record(&mut fields);
+        //Name task spans after their task so every poll is labelled in the trace viewer
+        let name = match &fields.task {
+            Some(task) => format!("{} {task}", attrs.metadata().name()),
+            None => attrs.metadata().name().to_string(),
+        };
+        let data = Box::new(SpanData {
+            name,
+            args: fields.to_json(),
+            refs: AtomicUsize::new(1),
+        });
+        span::Id::from_u64(Box::into_raw(data) as u64)
+    }
+
+    fn record(&self, _span: &span::Id, _values: &span::Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let name = fields
+            .message
+            .take()
+            .unwrap_or_else(|| event.metadata().name().to_string());
+        STATE.lock().unwrap().push(name, 'i', fields.to_json());
+    }
+
+    fn enter(&self, span: &span::Id) {
+        if !is_recording() {
+            return;
+        }
+        let data = SpanData::from_id(span);
+        let (name, args) = (data.name.clone(), data.args.clone());
+        STATE.lock().unwrap().push(name, 'B', args);
+    }
+
+    fn exit(&self, span: &span::Id) {
+        if !is_recording() {
+            return;
+        }
+        let name = SpanData::from_id(span).name.clone();
+        STATE.lock().unwrap().push(name, 'E', "{}".to_string());
+    }
+
+    fn clone_span(&self, span: &span::Id) -> span::Id {
+        SpanData::from_id(span).refs.fetch_add(1, Ordering::Relaxed);
+        span.clone()
+    }
+
+    fn try_close(&self, span: span::Id) -> bool {
+        if SpanData::from_id(&span).refs.fetch_sub(1, Ordering::AcqRel) != 1 {
+            return false;
+        }
+        // SAFETY: that was the last reference, so nothing else can reach the allocation
+        drop(unsafe { Box::from_raw(span.into_u64() as *mut SpanData) });
+        true
+    }
+}
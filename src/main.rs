@@ -4,14 +4,31 @@ use std::time::Duration;
 
 use eframe::egui;
 use egui_extras::install_image_loaders;
-use tokio::runtime::Runtime;
 
-use crate::impls::app::App;
+use crate::impls::{
+    app::App,
+    thread_names::{TOKIO_BLOCKING, name_current_thread, name_tokio_worker, spawn_named},
+    trace::ChromeTrace,
+};
 
 fn main() -> eframe::Result {
-    let runtime = Runtime::new().unwrap();
+    //Before the benchmarks or the UI get to restrict the main thread
+    impls::affinity::startup_cpus();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(code) = impls::bench_history::run_cli(&args) {
+        std::process::exit(code);
+    }
+
+    name_current_thread("ui");
+    tracing::subscriber::set_global_default(ChromeTrace).expect("no other tracing subscriber");
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .thread_name(TOKIO_BLOCKING)
+        .on_thread_park(name_tokio_worker)
+        .build()
+        .unwrap();
     let _enter = runtime.enter();
-    std::thread::spawn(move || {
+    spawn_named("tokio-main".to_string(), move || {
         runtime.block_on(async {
             loop {
                 tokio::time::sleep(Duration::from_secs(3600)).await;